@@ -1,111 +1,76 @@
 use std::fs::read_to_string;
 use std::error::Error;
 
-use num_enum::{TryFromPrimitive};
-
 use std::ops::Range;
-use std::convert::TryFrom;
-
-
-type MyResult<T> = Result<T, Box<dyn Error>>;
-
-#[repr(usize)]
-#[derive(Debug, Eq, PartialEq, TryFromPrimitive)]
-enum Op {
-    Add = 1,
-    Mult = 2,
-    Halt = 99
-}
-
-#[derive(Debug)]
-struct Intcode {
-    code: Vec<usize>,
-    iptr: usize
-}
-
-impl Intcode {
-    fn new() -> Self {
-        Self{
-            code: read_to_string("src/input.txt").unwrap().split(',').map(|item| item.parse::<usize>().unwrap()).collect(),
-            iptr: 0
-        }
-    }
-
-    fn load_input(self: &mut Self, param1: usize, param2: usize) {
-        self.code[1] = param1;
-        self.code[2] = param2;
-    }
-
-    fn get_args(self: &Self) -> (usize, usize) {
-        (self.code[self.code[self.iptr + 1]], self.code[self.code[self.iptr + 2]])
-    }
 
-    fn store_result(self: &mut Self, result: usize) {
-        let index = self.code[self.iptr + 3];
-        self.code[index] = result
-    }
+use intcode::patch::{Patch, Program};
+use intcode::symbolic::Symbolic;
+use intcode::{Limits, RunState, VmError, IO};
 
-    fn finished(self: &Self) -> bool {
-        self.iptr >= self.code.len()
-    }
 
-    fn op(self: &Self) -> Op {
-        Op::try_from(self.code[self.iptr]).unwrap()
-    }
-
-    fn next(self: &mut Self) {
-        // TODO: needs to parametrise depending on current op
-        self.iptr += 4
-    }
+type MyResult<T> = Result<T, Box<dyn Error>>;
 
-    fn run(self: &mut Self) -> usize {
-        while !self.finished() {
-            match self.op() {
-                Op::Add => {
-                    let (arg1, arg2) = self.get_args();
-                    self.store_result(arg1 + arg2);
-                },
-                Op::Mult => {
-                    let (arg1, arg2) = self.get_args();
-                    self.store_result(arg1 * arg2);
-                },
-                Op::Halt => break,
-            }
+// the program is a straight line of a few dozen instructions, anything longer is a runaway
+const MAX_STEPS: u64 = 10_000;
 
-            self.next()
+/// Value left at address 0, `None` if the program did not halt within `MAX_STEPS`.
+fn run(program: &Program, noun: i64, verb: i64) -> MyResult<Option<i64>> {
+    let inputs: Patch = vec![(1, noun), (2, verb)].into_iter().collect();
+    let mut program = program.patched(&[&inputs])?;
 
-        }
-        self.code[0]
+    let limits = Limits{max_steps: Some(MAX_STEPS), ..Limits::default()};
+    match program.run_with_limits(&mut IO::new(false), &mut IO::new(false), &limits)? {
+        RunState::Halted => Ok(Some(program.peek(0))),
+        _ => Ok(None),
     }
 }
 
-fn part1() -> usize {
-    let mut program = Intcode::new();
-    program.load_input(12, 2);
+fn part1(data: &str) -> MyResult<i64> {
+    Ok(run(&Program::new(data)?, 12, 2)?.ok_or("the program does not halt")?)
+}
 
-    program.run()
+const EXPECTED: i64 = 19690720;
+
+/// Noun and verb giving `EXPECTED`, found by running the program once with both left open.
+fn solve(data: &str) -> MyResult<Option<(i64, i64)>> {
+    let mut program = Symbolic::new(&intcode::parse(data)?);
+    let noun = program.variable("noun", 0..100);
+    let verb = program.variable("verb", 0..100);
+    program.poke(1, noun);
+    program.poke(2, verb);
+    program.run(MAX_STEPS)?;
+    Ok(program.solve(&program.peek(0), EXPECTED)?.map(|values| (values[0], values[1])))
 }
 
-fn part2() -> usize {
-    const EXPECTED: usize = 19690720;
+fn part2(data: &str) -> MyResult<i64> {
+    match solve(data) {
+        Ok(Some((noun, verb))) => return Ok(100 * noun + verb),
+        Ok(None) => return Err(format!("correct pair of noun/verb is not found for {}", EXPECTED).into()),
+        // fall back to trying every pair
+        Err(error) => match error.downcast_ref::<VmError>() {
+            Some(VmError::TooComplex { .. }) => {},
+            _ => return Err(error),
+        },
+    }
+
+    let program = Program::new(data)?;
     for noun in (Range{start: 0, end: 100}) {
         for verb in (Range{start:0, end: 100}) {
-            let mut program = Intcode::new();
-            program.load_input(noun, verb);
-
-            let result = program.run();
-            if result == EXPECTED {
-                return 100 * noun + verb
+            let result = run(&program, noun, verb)?;
+            if result == Some(EXPECTED) {
+                return Ok(100 * noun + verb)
             }
         }
     }
 
-    panic!("Correct pair of noun/verb is not found for {}", EXPECTED);
+    Err(format!("correct pair of noun/verb is not found for {}", EXPECTED).into())
 }
 
 
 fn main() -> MyResult<()> {
-    println!("Result Part 1: {:?}", part1());
-    println!("Result Part 2: {:?}", part2());
+    let data: String = read_to_string("src/input.txt")?;
+
+    println!("Result Part 1: {:?}", part1(&data)?);
+    println!("Result Part 2: {:?}", part2(&data)?);
     Ok(())
 }
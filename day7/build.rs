@@ -0,0 +1,18 @@
+//! Translates the puzzle input into Rust for part 1, see `intcode::aot`.
+
+use std::env;
+use std::error::Error;
+use std::fs::{read_to_string, write};
+use std::path::Path;
+
+use intcode::aot::compile;
+
+
+fn main() -> Result<(), Box<dyn Error>> {
+    println!("cargo:rerun-if-changed=src/input.txt");
+
+    let code = intcode::parse(&read_to_string("src/input.txt")?)?;
+    let path = Path::new(&env::var("OUT_DIR")?).join("amplifier.rs");
+    write(path, compile(&code, "amplifier").source)?;
+    Ok(())
+}
@@ -0,0 +1,223 @@
+//! Ahead-of-time translation of Intcode programs into Rust source.
+//!
+//! `compile` turns every instruction reachable from address 0 (see `entry_points`)
+//! into one arm of a `match iptr` loop. Parameters are still read from memory, so patched
+//! data cells such as day 2's noun and verb need no recompilation, only the instruction
+//! words are baked into the code. Whenever that assumption breaks, i.e. a write hits an
+//! instruction word, a jump lands outside the known instructions or the memory handed in
+//! does not match the compiled program, the generated code hands its state over to
+//! `Intcode` and lets the interpreter finish the run.
+//!
+//! The generated code works on a `PagedMemory`, so it runs into the same memory limits as
+//! the interpreter. A build script can compile a puzzle input, as day 7 does for part 1.
+
+use std::collections::BTreeSet;
+
+use crate::disasm::{decode, Instruction, Param};
+use crate::{Mode, Opcode};
+
+
+/// Helpers shared by every generated module, `interpret` is the way out to the interpreter.
+const PRELUDE: &str = "
+    use intcode::{InputSource, Intcode, LimitExceeded, Memory, OutputSink, PagedMemory, RunState, VmError, VmResult};
+
+    fn addr_of(iptr: usize, target: i64) -> VmResult<usize> {
+        if target < 0 {
+            return Err(VmError::NegativeAddress { addr: iptr, target })
+        }
+        Ok(target as usize)
+    }
+
+    fn limited(iptr: usize) -> impl Fn(LimitExceeded) -> VmError {
+        move |limit| VmError::MemoryLimit { addr: iptr, target: limit.target }
+    }
+
+    /// The program cells are kept in `mem`, the cells past them stay in `memory`.
+    fn load(mem: &[i64], memory: &PagedMemory, iptr: usize, target: i64) -> VmResult<i64> {
+        let addr = addr_of(iptr, target)?;
+        match mem.get(addr) {
+            Some(&value) => Ok(value),
+            None => memory.read(addr).map_err(limited(iptr)),
+        }
+    }
+
+    /// Writes the cell, `true` if it holds a compiled instruction word.
+    fn store(mem: &mut [i64], memory: &mut PagedMemory, iptr: usize, addr: usize, value: i64) -> VmResult<bool> {
+        if addr < mem.len() {
+            mem[addr] = value;
+            return Ok(STARTS.binary_search_by_key(&addr, |&(start, _)| start).is_ok())
+        }
+        memory.write(addr, value).map_err(limited(iptr))?;
+        Ok(false)
+    }
+
+    fn jump(mem: &[i64], iptr: usize, target: i64) -> VmResult<usize> {
+        if target < 0 || target as usize >= mem.len() {
+            return Err(VmError::JumpOutOfBounds { addr: iptr, target })
+        }
+        Ok(target as usize)
+    }
+
+    /// Puts the program cells back into `memory`.
+    fn merge(mem: &[i64], mut memory: PagedMemory) -> PagedMemory {
+        for (addr, &value) in mem.iter().enumerate() {
+            // the program pages are allocated on load, so this never hits a limit
+            let _ = memory.write(addr, value);
+        }
+        memory
+    }
+
+    fn interpret<I: InputSource, O: OutputSink>(memory: PagedMemory, iptr: usize, base: i64, input: &mut I, output: &mut O) -> VmResult<PagedMemory> {
+        let mut vm = Intcode::resume(memory, iptr, base)?;
+        loop {
+            match vm.run(input, output)? {
+                RunState::Halted => return Ok(vm.memory),
+                RunState::NeedsInput => return Err(VmError::MissingInput { addr: vm.iptr }),
+                RunState::Output(value) => output.write_output(value),
+                RunState::Yielded | RunState::Limited(_) => {},
+            }
+        }
+    }
+";
+
+
+/// Generated source with what was found out about the program on the way.
+#[derive(Debug, Clone)]
+pub struct Compiled {
+    pub source: String,
+    // (instruction, target) pairs of writes into instruction words known without running
+    pub self_modifying: Vec<(usize, usize)>,
+    // number of instructions turned into match arms
+    pub instructions: usize
+}
+
+fn read(addr: usize, index: usize, param: &Param) -> String {
+    let cell = format!("mem[{}]", addr + index + 1);
+    match param.mode {
+        Mode::Position => format!("load(&mem, &memory, {}, {})?", addr, cell),
+        Mode::Immediate => cell,
+        Mode::Relative => format!("load(&mem, &memory, {}, base.wrapping_add({}))?", addr, cell),
+    }
+}
+
+fn target(addr: usize, index: usize, param: &Param) -> String {
+    let cell = format!("mem[{}]", addr + index + 1);
+    match param.mode {
+        Mode::Relative => format!("addr_of({}, base.wrapping_add({}))?", addr, cell),
+        _ => format!("addr_of({}, {})?", addr, cell),
+    }
+}
+
+/// Body of the match arm executing the instruction at `addr`.
+fn arm(addr: usize, opcode: Opcode, params: &[Param]) -> String {
+    let next = addr + params.len() + 1;
+    // falling back right after the write lets the interpreter decode the changed word
+    let store = |value: &str| format!(
+        "let addr = {}; if store(&mut mem, &mut memory, {}, addr, {})? {{ return interpret(merge(&mem, memory), {}, base, input, output) }} iptr = {};",
+        target(addr, params.len() - 1, &params[params.len() - 1]), addr, value, next, next
+    );
+    match opcode {
+        Opcode::Add | Opcode::Mult | Opcode::Less | Opcode::Equals => {
+            let (value1, value2) = (read(addr, 0, &params[0]), read(addr, 1, &params[1]));
+            let value = match opcode {
+                Opcode::Add => "value1.wrapping_add(value2)",
+                Opcode::Mult => "value1.wrapping_mul(value2)",
+                Opcode::Less => "(value1 < value2) as i64",
+                _ => "(value1 == value2) as i64",
+            };
+            format!("let value1 = {}; let value2 = {}; {}", value1, value2, store(value))
+        },
+        Opcode::Input => format!(
+            "let addr = {}; let value = input.read_input().ok_or(VmError::MissingInput {{ addr: {} }})?; \
+             if store(&mut mem, &mut memory, {}, addr, value)? {{ return interpret(merge(&mem, memory), {}, base, input, output) }} iptr = {};",
+            target(addr, 0, &params[0]), addr, addr, next, next
+        ),
+        Opcode::Output => format!("output.write_output({}); iptr = {};", read(addr, 0, &params[0]), next),
+        Opcode::JumpTrue | Opcode::JumpFalse => format!(
+            "let value = {}; let target = {}; iptr = if value {} 0 {{ jump(&mem, {}, target)? }} else {{ {} }};",
+            read(addr, 0, &params[0]), read(addr, 1, &params[1]),
+            if opcode == Opcode::JumpTrue { "!=" } else { "==" }, addr, next
+        ),
+        Opcode::AdjustBase => format!("base = base.wrapping_add({}); iptr = {};", read(addr, 0, &params[0]), next),
+        Opcode::Halt => "return Ok(merge(&mem, memory))".to_string(),
+    }
+}
+
+/// Instructions reachable from 0, plus the ones right after unconditional jumps.
+///
+/// Calls push the address after the jump as the return address and come back
+/// through a relative jump, which `disasm::reachable` can not follow.
+fn entry_points(code: &[i64]) -> BTreeSet<usize> {
+    let mut starts: BTreeSet<usize> = BTreeSet::new();
+    let mut queue: Vec<usize> = vec![0];
+    while let Some(addr) = queue.pop() {
+        if starts.contains(&addr) {
+            continue;
+        }
+        if let Some(instruction) = decode(code, addr) {
+            starts.insert(addr);
+            if let Instruction::Code { opcode: Opcode::JumpTrue, .. } | Instruction::Code { opcode: Opcode::JumpFalse, .. } = instruction {
+                queue.push(addr + instruction.size());
+            }
+            queue.extend(instruction.successors());
+        }
+    }
+    starts
+}
+
+/// Translates `code` into a module `name` with `PROGRAM` and
+/// `run(memory, input, output) -> VmResult<PagedMemory>` returning the final memory.
+///
+/// The generated `run` keeps going until the program halts: blocking streams are not
+/// supported and an empty input is a `MissingInput` error. `name` has to be a Rust identifier.
+pub fn compile(code: &[i64], name: &str) -> Compiled {
+    let starts: BTreeSet<usize> = entry_points(code);
+    let mut arms: Vec<String> = vec![];
+    let mut self_modifying: Vec<(usize, usize)> = vec![];
+
+    for &addr in &starts {
+        if let Some(Instruction::Code { opcode, params, .. }) = decode(code, addr) {
+            if let Some(last) = params.last() {
+                if opcode.writes() && last.mode == Mode::Position && last.value >= 0 && starts.contains(&(last.value as usize)) {
+                    self_modifying.push((addr, last.value as usize));
+                }
+            }
+            arms.push(format!("                {} => {{ {} }},\n", addr, arm(addr, opcode, &params)));
+        }
+    }
+
+    let cells: Vec<String> = code.iter().map(|value| value.to_string()).collect();
+    let words: Vec<String> = starts.iter().map(|&addr| format!("({}, {})", addr, code[addr])).collect();
+
+    let mut source = format!(
+        "// Generated by intcode::aot from a program of {} cells, do not edit.\n\
+         #[allow(unused, clippy::all)]\npub mod {} {{{}\n    \
+         pub const PROGRAM: &[i64] = &[{}];\n\n    \
+         // compiled instruction addresses with their instruction words\n    \
+         const STARTS: &[(usize, i64)] = &[{}];\n\n",
+        code.len(), name, PRELUDE, cells.join(", "), words.join(", "));
+    source.push_str(
+"    pub fn run<I: InputSource, O: OutputSink>(mut memory: PagedMemory, input: &mut I, output: &mut O) -> VmResult<PagedMemory> {
+        if memory.len() != PROGRAM.len() || STARTS.iter().any(|&(addr, word)| memory.read(addr) != Ok(word)) {
+            return interpret(memory, 0, 0, input, output)
+        }
+        let mut mem: Vec<i64> = memory.to_vec();
+        let mut iptr: usize = 0;
+        let mut base: i64 = 0;
+        while iptr < mem.len() {
+            match iptr {
+");
+    for arm in &arms {
+        source.push_str(arm);
+    }
+    source.push_str(
+"                _ => return interpret(merge(&mem, memory), iptr, base, input, output),
+            }
+        }
+        Ok(merge(&mem, memory))
+    }
+}
+");
+
+    Compiled{source, self_modifying, instructions: arms.len()}
+}
@@ -0,0 +1,73 @@
+//! Driver for programs that talk in ASCII: text commands in, text and maps out.
+//!
+//! Values out of the ASCII range are not text, such a program uses them for a final
+//! answer, so they are passed through as plain numbers at their place in the output.
+
+use std::io::{BufRead, Write};
+
+use crate::{AsciiInput, AsciiOutput, Intcode, Memory, OutputSink, RunState, VmResult};
+
+
+/// Codes of `line` followed by a newline.
+pub fn encode_line(line: &str) -> Vec<i64> {
+    line.bytes().chain(Some(b'\n')).map(i64::from).collect()
+}
+
+/// The character for an ASCII code, `None` for any other value.
+pub fn to_char(value: i64) -> Option<char> {
+    if (0..=127).contains(&value) {
+        Some(value as u8 as char)
+    } else {
+        None
+    }
+}
+
+/// A piece of program output.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Chunk {
+    // consecutive ASCII codes
+    Text(String),
+    // a value that is not an ASCII code
+    Raw(i64),
+}
+
+/// Output of a program as text, with the values that are not ASCII codes where they occur.
+pub fn decode(values: &[i64]) -> Vec<Chunk> {
+    let mut output = AsciiOutput::default();
+    for &value in values {
+        output.write_output(value);
+    }
+    output.chunks
+}
+
+
+/// Line-based interactive session, like the `Stdin` prompt of day 5 but for text.
+///
+/// The output is shown every time the program stops for input, then one line is read from
+/// `reader` after a `$ ` prompt. Returns `NeedsInput` once `reader` has nothing more to give.
+pub fn terminal<M, R, W>(program: &mut Intcode<M>, reader: &mut R, writer: &mut W) -> VmResult<RunState>
+where M: Memory<Cell = i64>, R: BufRead, W: Write {
+    let mut input = AsciiInput::default();
+    loop {
+        let mut output = AsciiOutput::default();
+        let state = program.run(&mut input, &mut output)?;
+        // the terminal may be gone, the program is not affected by that
+        for chunk in output.chunks {
+            let _ = match chunk {
+                Chunk::Text(text) => write!(writer, "{}", text),
+                Chunk::Raw(value) => writeln!(writer, "> {}", value),
+            };
+        }
+        if state != RunState::NeedsInput {
+            return Ok(state)
+        }
+
+        let _ = write!(writer, "$ ");
+        let _ = writer.flush();
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => return Ok(state),
+            Ok(_) => input.push_line(line.trim_end_matches(&['\r', '\n'][..])),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Assembler for small hand-written Intcode programs.
+//!
+//! One statement per line, `;` starts a comment:
+//!
+//! ```text
+//! start:  in -> [x]
+//!         mul [x], #2 -> [x]
+//!         out [x]
+//!         jt #1, #start
+//! x:      .data 0
+//! ```
+//!
+//! Operands are `#value` (immediate), `[addr]` (position) and `[r+offset]` (relative),
+//! values are numbers or labels. The result operand may be separated with `->` instead of `,`.
+//! Listings printed by the disassembler are valid input, address prefixes like `0012:` are checked.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::{Mode, Opcode, OPCODES};
+
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+type AsmResult<T> = Result<T, AsmError>;
+
+
+#[derive(Debug)]
+enum Value {
+    Number(i64),
+    Label(String, i64), // label with a sign, `[r-label]` negates its address
+}
+
+#[derive(Debug)]
+enum Statement {
+    Code(Opcode, Vec<(Mode, Value)>),
+    Data(Vec<Value>),
+}
+
+impl Statement {
+    fn size(&self) -> usize {
+        match self {
+            Statement::Code(_, operands) => operands.len() + 1,
+            Statement::Data(values) => values.len(),
+        }
+    }
+}
+
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_'),
+        _ => false,
+    }
+}
+
+fn parse_value(text: &str, sign: i64) -> Result<Value, String> {
+    let text = text.trim();
+    if let Ok(number) = text.parse::<i64>() {
+        return Ok(Value::Number(sign * number))
+    }
+    if is_identifier(text) && text != "r" {
+        return Ok(Value::Label(text.to_string(), sign))
+    }
+    Err(format!("invalid value {:?}", text))
+}
+
+fn parse_operand(text: &str) -> Result<(Mode, Value), String> {
+    let text = text.trim();
+    if let Some(value) = text.strip_prefix('#') {
+        return Ok((Mode::Immediate, parse_value(value, 1)?))
+    }
+    if let Some(inner) = text.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+        let inner = inner.trim();
+        if inner == "r" {
+            return Ok((Mode::Relative, Value::Number(0)))
+        }
+        if let Some(offset) = inner.strip_prefix('r').map(str::trim_start) {
+            if let Some(offset) = offset.strip_prefix('+') {
+                return Ok((Mode::Relative, parse_value(offset, 1)?))
+            }
+            if let Some(offset) = offset.strip_prefix('-') {
+                return Ok((Mode::Relative, parse_value(offset, -1)?))
+            }
+        }
+        return Ok((Mode::Position, parse_value(inner, 1)?))
+    }
+    Err(format!("operand {:?} needs an addressing mode: #value, [addr] or [r+offset]", text))
+}
+
+fn parse_statement(text: &str) -> Result<Statement, String> {
+    let (word, rest) = match text.find(char::is_whitespace) {
+        Some(index) => (&text[..index], text[index..].trim()),
+        None => (text, ""),
+    };
+    let word = word.to_lowercase();
+
+    if word == ".data" || word == "data" {
+        if rest.is_empty() {
+            return Err("data directive without values".to_string())
+        }
+        let values = rest.split(',').map(|value| parse_value(value, 1)).collect::<Result<Vec<Value>, String>>()?;
+        return Ok(Statement::Data(values))
+    }
+
+    let opcode = OPCODES.iter().cloned().find(|opcode| opcode.mnemonic() == word)
+        .ok_or(format!("unknown mnemonic {:?}", word))?;
+    let operands: Vec<(Mode, Value)> = if rest.is_empty() {
+        vec![]
+    } else {
+        let rest = match rest.find("->") {
+            // the result operand is the last one, `->` is just a more readable comma
+            Some(index) if opcode.writes() => format!("{},{}", &rest[..index], &rest[index+2..]),
+            Some(_) => return Err(format!("{} does not write a result", word)),
+            None => rest.to_string(),
+        };
+        rest.split(',').map(|operand| operand.trim()).filter(|operand| !operand.is_empty())
+            .map(parse_operand).collect::<Result<Vec<(Mode, Value)>, String>>()?
+    };
+    if operands.len() != opcode.params_num() {
+        return Err(format!("{} takes {} operands, {} given", word, opcode.params_num(), operands.len()))
+    }
+    Ok(Statement::Code(opcode, operands))
+}
+
+
+/// Translates assembly text into memory cells, see the module docs for the syntax.
+pub fn assemble(text: &str) -> AsmResult<Vec<i64>> {
+    let mut labels: HashMap<String, usize> = HashMap::new();
+    let mut statements: Vec<(usize, Statement)> = vec![];
+    let mut addr: usize = 0;
+
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let error = |message: String| AsmError { line: number, message };
+        let mut line = line.split(';').next().unwrap_or("").trim();
+
+        while let Some(colon) = line.find(':') {
+            let label = line[..colon].trim();
+            if let Ok(expected) = label.parse::<usize>() {
+                if expected != addr {
+                    return Err(error(format!("address {} does not match the actual one {}", expected, addr)))
+                }
+            } else if is_identifier(label) && label != "r" {
+                if labels.insert(label.to_string(), addr).is_some() {
+                    return Err(error(format!("label {:?} is defined twice", label)))
+                }
+            } else {
+                break;
+            }
+            line = line[colon+1..].trim();
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        let statement = parse_statement(line).map_err(error)?;
+        addr += statement.size();
+        statements.push((number, statement));
+    }
+
+    let mut code: Vec<i64> = vec![];
+    for (number, statement) in statements {
+        let resolve = |value: &Value| -> AsmResult<i64> {
+            match value {
+                Value::Number(number) => Ok(*number),
+                Value::Label(label, sign) => labels.get(label).map(|&addr| sign * addr as i64)
+                    .ok_or(AsmError { line: number, message: format!("undefined label {:?}", label) }),
+            }
+        };
+        match statement {
+            Statement::Code(opcode, operands) => {
+                let mut word = opcode.code();
+                let mut scale = 100;
+                for (mode, _) in &operands {
+                    word += mode.digit() * scale;
+                    scale *= 10;
+                }
+                code.push(word);
+                for (_, value) in &operands {
+                    code.push(resolve(value)?);
+                }
+            },
+            Statement::Data(values) => {
+                for value in &values {
+                    code.push(resolve(value)?);
+                }
+            },
+        }
+    }
+    Ok(code)
+}
+
+/// Formats memory cells the way `Intcode::new` loads them.
+pub fn to_program(code: &[i64]) -> String {
+    let items: Vec<String> = code.iter().map(|value| value.to_string()).collect();
+    items.join(",")
+}
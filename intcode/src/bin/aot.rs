@@ -0,0 +1,34 @@
+use std::env;
+use std::error::Error;
+use std::fs::{read_to_string, write};
+
+use intcode::aot::compile;
+
+
+type MyResult<T> = Result<T, Box<dyn Error>>;
+
+const USAGE: &str = "usage: aot <program file> <module name> [output file]";
+
+fn main() -> MyResult<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        return Err(USAGE.into())
+    }
+    let name = &args[2];
+    let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!("{:?} is not a valid module name", name).into())
+    }
+
+    let compiled = compile(&intcode::parse(&read_to_string(&args[1])?)?, name);
+    for (addr, target) in &compiled.self_modifying {
+        eprintln!("warning: instruction at {} writes to the instruction at {}, the interpreter takes over from there", addr, target);
+    }
+    eprintln!("{} instructions compiled", compiled.instructions);
+    match args.get(3) {
+        Some(path) => write(path, compiled.source)?,
+        None => print!("{}", compiled.source),
+    }
+    Ok(())
+}
@@ -0,0 +1,20 @@
+use std::env;
+use std::error::Error;
+use std::fs::read_to_string;
+use std::io::{stdin, stdout};
+
+use intcode::Intcode;
+
+
+type MyResult<T> = Result<T, Box<dyn Error>>;
+
+const USAGE: &str = "usage: ascii <program file>";
+
+fn main() -> MyResult<()> {
+    let path = env::args().nth(1).ok_or(USAGE)?;
+    let mut program = Intcode::new(&read_to_string(path)?)?;
+
+    let state = intcode::ascii::terminal(&mut program, &mut stdin().lock(), &mut stdout())?;
+    println!("\n{:?}", state);
+    Ok(())
+}
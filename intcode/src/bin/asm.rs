@@ -0,0 +1,16 @@
+use std::env;
+use std::error::Error;
+use std::fs::read_to_string;
+
+use intcode::asm::{assemble, to_program};
+
+
+type MyResult<T> = Result<T, Box<dyn Error>>;
+
+fn main() -> MyResult<()> {
+    let path = env::args().nth(1).ok_or("usage: asm <assembly file>")?;
+    let code = assemble(&read_to_string(path)?)?;
+
+    println!("{}", to_program(&code));
+    Ok(())
+}
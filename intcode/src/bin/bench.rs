@@ -0,0 +1,62 @@
+use std::env;
+use std::error::Error;
+use std::fs::read_to_string;
+use std::time::{Duration, Instant};
+
+use intcode::fast::FastIntcode;
+use intcode::{Intcode, IO};
+
+
+type MyResult<T> = Result<T, Box<dyn Error>>;
+
+const USAGE: &str = "usage: bench <program file> [input ...] [--rounds n]
+e.g. cargo run --release --bin bench ../day9/src/input.txt 2";
+
+fn measure<F: FnMut() -> MyResult<Vec<i64>>>(rounds: usize, mut run: F) -> MyResult<(Duration, Vec<i64>)> {
+    let mut output: Vec<i64> = vec![];
+    let start = Instant::now();
+    for _ in 0..rounds {
+        output = run()?;
+    }
+    Ok((start.elapsed() / rounds as u32, output))
+}
+
+fn main() -> MyResult<()> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut rounds: usize = 5;
+    if let Some(index) = args.iter().position(|arg| arg == "--rounds") {
+        rounds = args.get(index + 1).ok_or(USAGE)?.parse()?;
+        args.drain(index..index + 2);
+    }
+    if args.is_empty() {
+        return Err(USAGE.into())
+    }
+    let data = read_to_string(&args[0])?;
+    let inputs: Vec<i64> = args[1..].iter().map(|value| value.parse()).collect::<Result<_, _>>()?;
+
+    let (slow, expected) = measure(rounds, || {
+        let mut program = Intcode::new(&data)?;
+        let mut input = IO::new(false);
+        input.extend_inputs(inputs.iter().cloned());
+        let mut output: Vec<i64> = vec![];
+        program.run(&mut input, &mut output)?;
+        Ok(output)
+    })?;
+    let (fast, actual) = measure(rounds, || {
+        let mut program = FastIntcode::new(&data)?;
+        let mut input = IO::new(false);
+        input.extend_inputs(inputs.iter().cloned());
+        let mut output: Vec<i64> = vec![];
+        program.run(&mut input, &mut output)?;
+        Ok(output)
+    })?;
+
+    if expected != actual {
+        return Err(format!("outputs differ: {:?} vs {:?}", expected, actual).into())
+    }
+    println!("output: {:?}", actual);
+    println!("Intcode::run      {:?} per run", slow);
+    println!("FastIntcode::run  {:?} per run", fast);
+    println!("speedup: {:.1}x", slow.as_secs_f64() / fast.as_secs_f64());
+    Ok(())
+}
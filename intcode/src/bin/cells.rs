@@ -0,0 +1,43 @@
+use std::collections::VecDeque;
+use std::env;
+use std::error::Error;
+use std::fs::read_to_string;
+
+use intcode::cell::{self, BigInt, Checked};
+use intcode::{Cell, Intcode, PagedMemory};
+
+
+type MyResult<T> = Result<T, Box<dyn Error>>;
+
+const USAGE: &str = "usage: cells <program file> [input ...] [--checked]";
+
+fn run<C: Cell>(data: &str, args: &[String]) -> MyResult<()> {
+    let mut program = Intcode::with_memory(PagedMemory::new(&cell::parse::<C>(data)?))?;
+    let mut input: VecDeque<C> = args.iter()
+        .map(|value| C::parse(value).ok_or_else(|| format!("invalid input {:?}", value)))
+        .collect::<Result<_, _>>()?;
+    let mut output: Vec<C> = vec![];
+
+    let state = program.run(&mut input, &mut output)?;
+    println!("{:?}", state);
+    for value in &output {
+        println!("{}", value);
+    }
+    Ok(())
+}
+
+fn main() -> MyResult<()> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let checked = args.iter().any(|arg| arg == "--checked");
+    args.retain(|arg| arg != "--checked");
+    if args.is_empty() {
+        return Err(USAGE.into())
+    }
+
+    let data = read_to_string(&args[0])?;
+    if checked {
+        run::<Checked>(&data, &args[1..])
+    } else {
+        run::<BigInt>(&data, &args[1..])
+    }
+}
@@ -0,0 +1,33 @@
+use std::env;
+use std::error::Error;
+use std::fs::{read_to_string, write};
+
+use intcode::cfg::build;
+
+
+type MyResult<T> = Result<T, Box<dyn Error>>;
+
+const USAGE: &str = "usage: cfg <program file> [dot file]";
+
+fn main() -> MyResult<()> {
+    let args: Vec<String> = env::args().collect();
+    let code = intcode::parse(&read_to_string(args.get(1).ok_or(USAGE)?)?)?;
+    let cfg = build(&code);
+
+    println!("{} blocks, {} edges", cfg.blocks.len(), cfg.edges.len());
+    for function in &cfg.functions {
+        let sites: Vec<String> = function.call_sites.iter().map(|site| site.to_string()).collect();
+        let frame = function.frame.map_or(String::from("?"), |frame| frame.to_string());
+        println!("function {:04}: {} blocks, frame {}, called from {}", function.entry, function.blocks.len(), frame, sites.join(", "));
+    }
+    for (start, end) in &cfg.unreachable {
+        println!("unreachable {:04}..{:04} ({} cells)", start, end, end - start);
+    }
+    println!("{} relative base adjustments, {} instructions with relative parameters",
+             cfg.base_adjustments.len(), cfg.relative_accesses.len());
+
+    if let Some(path) = args.get(2) {
+        write(path, cfg.to_dot())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use std::error::Error;
+
+use intcode::conformance::{check, corpus, Big, Checked, Engine, Fast, Interpreter};
+
+
+type MyResult<T> = Result<T, Box<dyn Error>>;
+
+fn main() -> MyResult<()> {
+    let cases = corpus();
+    let engines: Vec<Box<dyn Engine>> = vec![Box::new(Interpreter), Box::new(Fast), Box::new(Checked), Box::new(Big)];
+
+    let mut failed: usize = 0;
+    for engine in &engines {
+        let mismatches = check(engine.as_ref(), &cases);
+        let passed = cases.iter().filter(|case| mismatches.iter().all(|mismatch| mismatch.case != case.name)).count();
+        println!("{}: {}/{} cases passed", engine.name(), passed, cases.len());
+        for mismatch in &mismatches {
+            println!("  {}", mismatch);
+        }
+        failed += mismatches.len();
+    }
+    if failed > 0 {
+        return Err(format!("{} mismatches", failed).into())
+    }
+    Ok(())
+}
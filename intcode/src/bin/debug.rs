@@ -0,0 +1,32 @@
+use std::env;
+use std::error::Error;
+use std::fs::read_to_string;
+use std::io::{self, BufRead, Write};
+
+use intcode::Intcode;
+use intcode::debugger::Debugger;
+
+
+type MyResult<T> = Result<T, Box<dyn Error>>;
+
+fn main() -> MyResult<()> {
+    let path = env::args().nth(1).ok_or("usage: debug <program file>")?;
+    let mut debugger = Debugger::new(Intcode::new(&read_to_string(path)?)?);
+
+    println!("{}", debugger.current());
+    let stdin = io::stdin();
+    loop {
+        print!("(intcode) ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 || matches!(line.trim(), "quit" | "q") {
+            break;
+        }
+        match debugger.execute(&line) {
+            Ok(text) if text.is_empty() => {},
+            Ok(text) => println!("{}", text),
+            Err(message) => println!("error: {}", message),
+        }
+    }
+    Ok(())
+}
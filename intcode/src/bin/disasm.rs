@@ -0,0 +1,16 @@
+use std::env;
+use std::error::Error;
+use std::fs::read_to_string;
+
+use intcode::disasm::{disassemble, listing};
+
+
+type MyResult<T> = Result<T, Box<dyn Error>>;
+
+fn main() -> MyResult<()> {
+    let path = env::args().nth(1).ok_or("usage: disasm <program file>")?;
+    let code = intcode::parse(&read_to_string(path)?)?;
+
+    println!("{}", listing(&disassemble(&code)));
+    Ok(())
+}
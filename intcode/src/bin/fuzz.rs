@@ -0,0 +1,44 @@
+use std::env;
+use std::error::Error;
+use std::panic;
+
+use intcode::asm::to_program;
+use intcode::fuzz::{check, random_case};
+
+
+type MyResult<T> = Result<T, Box<dyn Error>>;
+
+const USAGE: &str = "usage: fuzz [iterations] [seed] [budget]";
+
+fn main() -> MyResult<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let arg = |index: usize, default: u64| -> MyResult<u64> {
+        match args.get(index) {
+            Some(value) => Ok(value.parse().map_err(|_| USAGE)?),
+            None => Ok(default),
+        }
+    };
+    let (iterations, seed, budget) = (arg(0, 10_000)?, arg(1, 1)?, arg(2, 10_000)? as usize);
+    if budget == 0 {
+        return Err("the budget has to be at least one instruction".into())
+    }
+
+    // panics are reported by `check` already
+    panic::set_hook(Box::new(|_| {}));
+
+    let mut failures: usize = 0;
+    for iteration in 0..iterations {
+        // every program can be reproduced on its own from its seed
+        let (code, inputs, split) = random_case(seed.wrapping_add(iteration), budget);
+
+        if let Err(problem) = check(&code, &inputs, budget, split) {
+            failures += 1;
+            println!("seed {}: {}\n  program: {}\n  inputs: {:?}", seed.wrapping_add(iteration), problem, to_program(&code), inputs);
+        }
+    }
+    println!("{} programs, {} failures", iterations, failures);
+    if failures > 0 {
+        return Err(format!("{} failures", failures).into())
+    }
+    Ok(())
+}
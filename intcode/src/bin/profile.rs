@@ -0,0 +1,40 @@
+use std::collections::VecDeque;
+use std::env;
+use std::error::Error;
+use std::fs::read_to_string;
+
+use intcode::profile::Profile;
+use intcode::Intcode;
+
+
+type MyResult<T> = Result<T, Box<dyn Error>>;
+
+const USAGE: &str = "usage: profile <program file> [input ...] [--annotate] [--top n]";
+
+fn main() -> MyResult<()> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let annotate = args.iter().any(|arg| arg == "--annotate");
+    args.retain(|arg| arg != "--annotate");
+    let mut top: usize = 10;
+    if let Some(index) = args.iter().position(|arg| arg == "--top") {
+        top = args.get(index + 1).ok_or(USAGE)?.parse()?;
+        args.drain(index..index + 2);
+    }
+    if args.is_empty() {
+        return Err(USAGE.into())
+    }
+
+    let data = read_to_string(&args[0])?;
+    let mut program = Intcode::new(&data)?;
+    let mut input: VecDeque<i64> = args[1..].iter().map(|value| value.parse()).collect::<Result<_, _>>()?;
+    let mut output: Vec<i64> = vec![];
+    let mut profile = Profile::new();
+
+    let state = program.run_traced(&mut input, &mut output, &mut profile)?;
+    println!("{:?}, output: {:?}\n", state, output);
+    println!("{}", profile.report(top));
+    if annotate {
+        println!("\n{}", profile.annotate(&intcode::parse(&data)?));
+    }
+    Ok(())
+}
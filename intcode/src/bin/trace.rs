@@ -0,0 +1,54 @@
+use std::collections::VecDeque;
+use std::env;
+use std::error::Error;
+use std::fs::{read_to_string, File};
+use std::io::{BufReader, BufWriter};
+
+use intcode::Intcode;
+use intcode::trace::{Replay, Trace};
+
+
+type MyResult<T> = Result<T, Box<dyn Error>>;
+
+const USAGE: &str = "usage: trace record <program file> <log file> [input ...]
+       trace history <program file> <log file> <addr>";
+
+fn record(data: &str, log: &str, inputs: &[String]) -> MyResult<()> {
+    let mut program = Intcode::new(data)?;
+    let mut input: VecDeque<i64> = inputs.iter().map(|value| value.parse()).collect::<Result<_, _>>()?;
+    let mut output: Vec<i64> = vec![];
+    let mut trace = Trace::new();
+
+    let state = program.run_traced(&mut input, &mut output, &mut trace)?;
+    trace.save(&mut BufWriter::new(File::create(log)?))?;
+    println!("{:?} after {} steps, output: {:?}", state, trace.events.len(), output);
+    Ok(())
+}
+
+fn history(data: &str, log: &str, addr: usize) -> MyResult<()> {
+    let trace = Trace::load(BufReader::new(File::open(log)?))?;
+    let replay = Replay::new(intcode::parse(data)?, trace);
+    if let Some(step) = replay.verify(Intcode::new(data)?)? {
+        println!("warning: the program does not reproduce the log from step {}", step);
+    }
+
+    for event in replay.history(addr) {
+        if let Some(write) = event.write {
+            println!("step {}: {:04} {:?} mem[{}] {} -> {}", event.step, event.iptr, event.op, addr, write.old, write.new);
+        }
+    }
+    Ok(())
+}
+
+fn main() -> MyResult<()> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 4 {
+        return Err(USAGE.into())
+    }
+    let data = read_to_string(&args[2])?;
+    match args[1].as_str() {
+        "record" => record(&data, &args[3], &args[4..]),
+        "history" => history(&data, &args[3], args.get(4).ok_or(USAGE)?.parse()?),
+        _ => Err(USAGE.into()),
+    }
+}
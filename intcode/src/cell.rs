@@ -0,0 +1,125 @@
+//! Numeric types for the memory cells of `Intcode`.
+//!
+//! Plain `i64` cells wrap around on overflow, like the original VM. `Checked` cells report
+//! an overflow as `VmError::Overflow` instead, `BigInt` cells never overflow and keep the
+//! exact value however large a program lets it grow. Opcodes, addresses and the relative
+//! base still have to fit into an `i64`, a larger value used as one of them is an
+//! `Overflow` as well.
+//!
+//! The cell type comes with the memory, e.g. `Intcode<PagedMemory<BigInt>>`.
+
+use std::convert::TryFrom;
+use std::fmt::{self, Debug, Display};
+
+pub use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+
+use crate::{VmError, VmResult};
+
+
+/// Value of a single memory cell.
+pub trait Cell: Clone + Debug + Display + Eq + Ord {
+    fn from_i64(value: i64) -> Self;
+
+    /// `None` if the value does not fit into an `i64`.
+    fn to_i64(&self) -> Option<i64>;
+
+    /// `None` on an overflow the cell type reports.
+    fn try_add(&self, other: &Self) -> Option<Self>;
+
+    /// `None` on an overflow the cell type reports.
+    fn try_mul(&self, other: &Self) -> Option<Self>;
+
+    fn parse(token: &str) -> Option<Self>;
+
+    fn is_zero(&self) -> bool {
+        *self == Self::from_i64(0)
+    }
+}
+
+impl Cell for i64 {
+    fn from_i64(value: i64) -> Self {
+        value
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        Some(*self)
+    }
+
+    fn try_add(&self, other: &Self) -> Option<Self> {
+        Some(self.wrapping_add(*other))
+    }
+
+    fn try_mul(&self, other: &Self) -> Option<Self> {
+        Some(self.wrapping_mul(*other))
+    }
+
+    fn parse(token: &str) -> Option<Self> {
+        token.parse().ok()
+    }
+}
+
+
+/// An `i64` cell for which overflow is an error.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Hash, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Checked(pub i64);
+
+impl Display for Checked {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Cell for Checked {
+    fn from_i64(value: i64) -> Self {
+        Checked(value)
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        Some(self.0)
+    }
+
+    fn try_add(&self, other: &Self) -> Option<Self> {
+        self.0.checked_add(other.0).map(Checked)
+    }
+
+    fn try_mul(&self, other: &Self) -> Option<Self> {
+        self.0.checked_mul(other.0).map(Checked)
+    }
+
+    fn parse(token: &str) -> Option<Self> {
+        token.parse().ok().map(Checked)
+    }
+}
+
+
+impl Cell for BigInt {
+    fn from_i64(value: i64) -> Self {
+        BigInt::from(value)
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        i64::try_from(self).ok()
+    }
+
+    fn try_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn try_mul(&self, other: &Self) -> Option<Self> {
+        Some(self * other)
+    }
+
+    fn parse(token: &str) -> Option<Self> {
+        token.parse().ok()
+    }
+}
+
+
+/// Parses the comma-separated program text, like `intcode::parse`.
+pub fn parse<C: Cell>(data: &str) -> VmResult<Vec<C>> {
+    data.trim().split(',').enumerate()
+        .map(|(index, item)| C::parse(item.trim()).ok_or(VmError::Parse { index, token: item.to_string() }))
+        .collect()
+}
@@ -0,0 +1,299 @@
+//! Control-flow graph of an Intcode program, built without running it.
+//!
+//! The walk starts at address 0 and follows fall-through and immediate jump targets, like
+//! `disasm::reachable`. On top of that it recognizes the calling convention of day 9 style
+//! programs: the return address is stored into a relative-base stack slot right before an
+//! unconditional jump, and the callee returns with an unconditional jump through a relative
+//! parameter. The code after such a call is reachable as well, even though only a
+//! computed jump ever gets there.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::disasm::{decode, Instruction, Param};
+use crate::{Mode, Opcode};
+
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum EdgeKind {
+    FallThrough,
+    Jump,
+    Call,
+    // from the calling block to the return address, standing in for the callee's return
+    AfterCall,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Block {
+    pub start: usize,
+    // one past the last cell of the last instruction
+    pub end: usize,
+    pub instructions: Vec<Instruction>,
+    // ends with a jump through a position or relative parameter
+    pub dynamic_exit: bool,
+    // ends with the return jump of the calling convention
+    pub returns: bool
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Function {
+    pub entry: usize,
+    // addresses of the jumps calling it
+    pub call_sites: Vec<usize>,
+    // starts of the blocks reachable from the entry without following calls
+    pub blocks: BTreeSet<usize>,
+    // stack frame allocated by an `ARB #n` at the entry
+    pub frame: Option<i64>
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Cfg {
+    pub blocks: BTreeMap<usize, Block>,
+    pub edges: Vec<Edge>,
+    pub functions: Vec<Function>,
+    // cell ranges no reachable instruction covers, end exclusive
+    pub unreachable: Vec<(usize, usize)>,
+    // every `AdjustBase` with its parameter
+    pub base_adjustments: Vec<(usize, Param)>,
+    // instructions with at least one relative parameter
+    pub relative_accesses: BTreeSet<usize>
+}
+
+
+/// The (jump address, callee, return address) of a call made by the store at `addr`.
+fn call_at(code: &[i64], addr: usize) -> Option<(usize, usize, usize)> {
+    let (value, slot) = match decode(code, addr)? {
+        Instruction::Code { opcode: Opcode::Add, params, .. } if params[0].mode == Mode::Immediate && params[1].mode == Mode::Immediate =>
+            (params[0].value.wrapping_add(params[1].value), params[2]),
+        Instruction::Code { opcode: Opcode::Mult, params, .. } if params[0].mode == Mode::Immediate && params[1].mode == Mode::Immediate =>
+            (params[0].value.wrapping_mul(params[1].value), params[2]),
+        _ => return None,
+    };
+    let jump = addr + 4;
+    let callee = match unconditional_target(code, jump)? {
+        Param { mode: Mode::Immediate, value } if value >= 0 => value as usize,
+        _ => return None,
+    };
+    if slot.mode != Mode::Relative || value != (jump + 3) as i64 {
+        return None
+    }
+    Some((jump, callee, jump + 3))
+}
+
+/// Target parameter of the jump at `addr` if it is always taken.
+fn unconditional_target(code: &[i64], addr: usize) -> Option<Param> {
+    match decode(code, addr)? {
+        Instruction::Code { opcode, params, .. } if opcode == Opcode::JumpTrue || opcode == Opcode::JumpFalse => {
+            let taken = params[0].mode == Mode::Immediate && (params[0].value != 0) == (opcode == Opcode::JumpTrue);
+            if taken { Some(params[1]) } else { None }
+        },
+        _ => None,
+    }
+}
+
+fn is_return(code: &[i64], addr: usize) -> bool {
+    matches!(unconditional_target(code, addr), Some(Param { mode: Mode::Relative, .. }))
+}
+
+
+/// Walks the program and splits the reachable instructions into basic blocks.
+pub fn build(code: &[i64]) -> Cfg {
+    let mut instructions: BTreeMap<usize, Instruction> = BTreeMap::new();
+    let mut leaders: BTreeSet<usize> = BTreeSet::new();
+    // jump address to (callee, return address)
+    let mut calls: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+    let mut queue: Vec<usize> = vec![0];
+    leaders.insert(0);
+
+    while let Some(addr) = queue.pop() {
+        if instructions.contains_key(&addr) {
+            continue;
+        }
+        let instruction = match decode(code, addr) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+        if let Instruction::Code { opcode, .. } = instruction {
+            if opcode == Opcode::JumpTrue || opcode == Opcode::JumpFalse {
+                leaders.extend(instruction.successors());
+                leaders.insert(addr + instruction.size());
+            }
+        }
+        if let Some((jump, callee, ret)) = call_at(code, addr) {
+            calls.insert(jump, (callee, ret));
+            leaders.insert(ret);
+            queue.push(ret);
+        }
+        queue.extend(instruction.successors());
+        instructions.insert(addr, instruction);
+    }
+
+    let mut cfg = Cfg::default();
+    for (&addr, instruction) in &instructions {
+        let size = instruction.size();
+        if let Instruction::Code { opcode, params, .. } = instruction {
+            if *opcode == Opcode::AdjustBase {
+                cfg.base_adjustments.push((addr, params[0]));
+            }
+            if params.iter().any(|param| param.mode == Mode::Relative) {
+                cfg.relative_accesses.insert(addr);
+            }
+        }
+        // a jump into the middle of another instruction starts a block of its own anyway
+        let start = match cfg.blocks.range(..=addr).next_back() {
+            Some((&start, block)) if block.end == addr && !leaders.contains(&addr) && !ends_block(&block.instructions) => start,
+            _ => {
+                cfg.blocks.insert(addr, Block{start: addr, end: addr, instructions: vec![], dynamic_exit: false, returns: false});
+                addr
+            },
+        };
+        if let Some(block) = cfg.blocks.get_mut(&start) {
+            block.instructions.push(instruction.clone());
+            block.end = addr + size;
+        }
+    }
+
+    for block in cfg.blocks.values_mut() {
+        let last = match block.instructions.last() {
+            Some(last) => last.clone(),
+            None => continue,
+        };
+        block.returns = is_return(code, last.addr());
+        if let Instruction::Code { opcode: Opcode::JumpTrue, params, .. } | Instruction::Code { opcode: Opcode::JumpFalse, params, .. } = &last {
+            block.dynamic_exit = params[1].mode != Mode::Immediate;
+        }
+
+        if let Some(&(callee, ret)) = calls.get(&last.addr()) {
+            cfg.edges.push(Edge{from: block.start, to: callee, kind: EdgeKind::Call});
+            cfg.edges.push(Edge{from: block.start, to: ret, kind: EdgeKind::AfterCall});
+            continue;
+        }
+        let target = if ends_block(&block.instructions) { jump_target(&last) } else { None };
+        for next in last.successors() {
+            let kind = if Some(next) == target { EdgeKind::Jump } else { EdgeKind::FallThrough };
+            cfg.edges.push(Edge{from: block.start, to: next, kind});
+        }
+    }
+
+    let mut callees: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (&jump, &(callee, _)) in &calls {
+        callees.entry(callee).or_default().push(jump);
+    }
+    for (entry, call_sites) in callees {
+        let blocks = cfg.function_blocks(entry);
+        let frame = match instructions.get(&entry) {
+            Some(Instruction::Code { opcode: Opcode::AdjustBase, params, .. }) if params[0].mode == Mode::Immediate => Some(params[0].value),
+            _ => None,
+        };
+        cfg.functions.push(Function{entry, call_sites, blocks, frame});
+    }
+
+    let mut covered: Vec<bool> = vec![false; code.len()];
+    for (&addr, instruction) in &instructions {
+        for cell in covered.iter_mut().skip(addr).take(instruction.size()) {
+            *cell = true;
+        }
+    }
+    let mut addr: usize = 0;
+    while addr < code.len() {
+        if covered[addr] {
+            addr += 1;
+            continue;
+        }
+        let start = addr;
+        while addr < code.len() && !covered[addr] {
+            addr += 1;
+        }
+        cfg.unreachable.push((start, addr));
+    }
+    cfg
+}
+
+fn ends_block(instructions: &[Instruction]) -> bool {
+    matches!(
+        instructions.last(),
+        Some(Instruction::Code { opcode: Opcode::JumpTrue, .. }) | Some(Instruction::Code { opcode: Opcode::JumpFalse, .. }) | Some(Instruction::Code { opcode: Opcode::Halt, .. })
+    )
+}
+
+fn jump_target(instruction: &Instruction) -> Option<usize> {
+    match instruction {
+        Instruction::Code { params, .. } if params.len() == 2 && params[1].mode == Mode::Immediate && params[1].value >= 0 => Some(params[1].value as usize),
+        _ => None,
+    }
+}
+
+
+impl Cfg {
+    /// Blocks reachable from `entry` without following calls, returns end the walk.
+    fn function_blocks(&self, entry: usize) -> BTreeSet<usize> {
+        let mut blocks: BTreeSet<usize> = BTreeSet::new();
+        let mut queue: Vec<usize> = vec![entry];
+        while let Some(start) = queue.pop() {
+            if !self.blocks.contains_key(&start) || !blocks.insert(start) {
+                continue;
+            }
+            queue.extend(self.edges.iter()
+                .filter(|edge| edge.from == start && edge.kind != EdgeKind::Call)
+                .map(|edge| edge.to));
+        }
+        blocks
+    }
+
+    /// The graph in GraphViz format, one node per block and one cluster per function.
+    pub fn to_dot(&self) -> String {
+        let mut lines: Vec<String> = vec![
+            "digraph intcode {".to_string(),
+            "    node [shape=box, fontname=monospace];".to_string(),
+        ];
+
+        let mut placed: BTreeSet<usize> = BTreeSet::new();
+        for function in &self.functions {
+            lines.push(format!("    subgraph cluster_{} {{", function.entry));
+            lines.push(format!("        label=\"function {}\";", function.entry));
+            for &start in &function.blocks {
+                if placed.insert(start) {
+                    lines.push(format!("        {}", self.node(start)));
+                }
+            }
+            lines.push("    }".to_string());
+        }
+        for &start in self.blocks.keys() {
+            if placed.insert(start) {
+                lines.push(format!("    {}", self.node(start)));
+            }
+        }
+
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::FallThrough => "",
+                EdgeKind::Jump => " [color=blue]",
+                EdgeKind::Call => " [color=red, style=bold]",
+                EdgeKind::AfterCall => " [style=dashed]",
+            };
+            lines.push(format!("    b{} -> b{}{};", edge.from, edge.to, style));
+        }
+        for &(start, end) in &self.unreachable {
+            lines.push(format!("    u{} [label=\"unreachable {:04}..{:04}\", style=dashed];", start, start, end));
+        }
+        lines.push("}".to_string());
+        format!("{}\n", lines.join("\n"))
+    }
+
+    fn node(&self, start: usize) -> String {
+        let block = &self.blocks[&start];
+        let mut label: String = block.instructions.iter().map(|instruction| format!("{}\\l", instruction)).collect();
+        if block.returns {
+            label.push_str("(return)\\l");
+        } else if block.dynamic_exit {
+            label.push_str("(computed jump)\\l");
+        }
+        format!("b{} [label=\"{}\"];", start, label)
+    }
+}
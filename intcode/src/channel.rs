@@ -0,0 +1,31 @@
+//! Running Intcode machines on their own threads, connected with `mpsc` channels.
+//!
+//! Every machine blocks on its input channel whenever it needs a value, so chains
+//! and loops of machines are wired up by handing one machine's `Sender` to the next.
+
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use crate::{Intcode, RunState, VmError, VmResult};
+
+
+/// Runs `program` until it halts, blocking on `input` whenever it asks for a value.
+///
+/// Fails with `MissingInput` if every sender of `input` is gone while the program still waits.
+pub fn run_connected(program: &mut Intcode, input: &mut Receiver<i64>, output: &mut Sender<i64>) -> VmResult<()> {
+    match program.run(input, output)? {
+        RunState::Halted => Ok(()),
+        // a receiver only runs dry once it is disconnected
+        _ => Err(VmError::MissingInput { addr: program.iptr }),
+    }
+}
+
+/// Moves `program` to a new thread and runs it there, see `run_connected`.
+///
+/// Joining the thread gives the halted machine back, e.g. to inspect its memory.
+pub fn spawn(mut program: Intcode, mut input: Receiver<i64>, mut output: Sender<i64>) -> JoinHandle<VmResult<Intcode>> {
+    thread::spawn(move || {
+        run_connected(&mut program, &mut input, &mut output)?;
+        Ok(program)
+    })
+}
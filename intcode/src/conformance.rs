@@ -0,0 +1,194 @@
+//! Conformance corpus built from the official examples of days 2, 5 and 9.
+//!
+//! Every execution engine is wrapped into an `Engine` and checked against the same cases,
+//! so a new engine only has to agree with the corpus to be trusted with the puzzles.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::cell::{self, BigInt, Cell};
+use crate::fast::FastIntcode;
+use crate::{Intcode, Memory, PagedMemory, RunState, VmError, VmResult};
+
+
+/// Runs a program to completion, giving back its output and final memory.
+pub trait Engine {
+    fn name(&self) -> &str;
+
+    fn execute(&self, code: &[i64], input: &[i64]) -> VmResult<(Vec<i64>, Vec<i64>)>;
+}
+
+/// The reference interpreter, `Intcode::run`.
+pub struct Interpreter;
+
+impl Engine for Interpreter {
+    fn name(&self) -> &str {
+        "Intcode"
+    }
+
+    fn execute(&self, code: &[i64], input: &[i64]) -> VmResult<(Vec<i64>, Vec<i64>)> {
+        execute_cells::<i64>(code, input)
+    }
+}
+
+pub struct Fast;
+
+impl Engine for Fast {
+    fn name(&self) -> &str {
+        "FastIntcode"
+    }
+
+    fn execute(&self, code: &[i64], input: &[i64]) -> VmResult<(Vec<i64>, Vec<i64>)> {
+        let mut program = FastIntcode::from_code(code.to_vec());
+        let mut input: VecDeque<i64> = input.iter().cloned().collect();
+        let mut output: Vec<i64> = vec![];
+        match program.run(&mut input, &mut output)? {
+            RunState::Halted => Ok((output, program.memory)),
+            _ => Err(VmError::MissingInput { addr: program.iptr }),
+        }
+    }
+}
+
+fn execute_cells<C: Cell>(code: &[i64], input: &[i64]) -> VmResult<(Vec<i64>, Vec<i64>)> {
+    let code: Vec<C> = code.iter().map(|&value| C::from_i64(value)).collect();
+    let mut program = Intcode::with_memory(PagedMemory::new(&code))?;
+    let mut input: VecDeque<C> = input.iter().map(|&value| C::from_i64(value)).collect();
+    let mut output: Vec<C> = vec![];
+    if program.run(&mut input, &mut output)? != RunState::Halted {
+        return Err(VmError::MissingInput { addr: program.iptr })
+    }
+    // the corpus only holds i64 values, anything larger is reported like an overflow
+    let back = |cells: &[C]| -> VmResult<Vec<i64>> {
+        cells.iter().map(|value| value.to_i64().ok_or(VmError::Overflow { addr: program.iptr })).collect()
+    };
+    Ok((back(&output)?, back(&program.memory.to_vec())?))
+}
+
+/// `Intcode` over `cell::Checked`, overflow is an error.
+pub struct Checked;
+
+impl Engine for Checked {
+    fn name(&self) -> &str {
+        "Intcode<Checked>"
+    }
+
+    fn execute(&self, code: &[i64], input: &[i64]) -> VmResult<(Vec<i64>, Vec<i64>)> {
+        execute_cells::<cell::Checked>(code, input)
+    }
+}
+
+/// `Intcode` over `BigInt`.
+pub struct Big;
+
+impl Engine for Big {
+    fn name(&self) -> &str {
+        "Intcode<BigInt>"
+    }
+
+    fn execute(&self, code: &[i64], input: &[i64]) -> VmResult<(Vec<i64>, Vec<i64>)> {
+        execute_cells::<BigInt>(code, input)
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Case {
+    pub name: String,
+    pub program: &'static str,
+    pub input: Vec<i64>,
+    pub output: Vec<i64>,
+    // expected beginning of the final memory, not checked when empty
+    pub memory: Vec<i64>
+}
+
+fn case(name: &str, program: &'static str, input: &[i64], output: &[i64], memory: &[i64]) -> Case {
+    Case{name: name.to_string(), program, input: input.to_vec(), output: output.to_vec(), memory: memory.to_vec()}
+}
+
+// name, program and the expected output for a given input
+type Comparison = (&'static str, &'static str, fn(i64) -> i64);
+
+const QUINE: &str = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99";
+const COMPARE_LARGE: &str = "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99";
+
+/// Every official example with its expected output or final memory.
+pub fn corpus() -> Vec<Case> {
+    let mut cases = vec![
+        case("day2 example", "1,9,10,3,2,3,11,0,99,30,40,50", &[], &[], &[3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50]),
+        case("day2 add", "1,0,0,0,99", &[], &[], &[2, 0, 0, 0, 99]),
+        case("day2 mult", "2,3,0,3,99", &[], &[], &[2, 3, 0, 6, 99]),
+        case("day2 mult past the code", "2,4,4,5,99,0", &[], &[], &[2, 4, 4, 5, 99, 9801]),
+        case("day2 self-modifying", "1,1,1,4,99,5,6,0,99", &[], &[], &[30, 1, 1, 4, 2, 5, 6, 0, 99]),
+        case("day5 echo", "3,0,4,0,99", &[42], &[42], &[42, 0, 4, 0, 99]),
+        case("day5 modes", "1002,4,3,4,33", &[], &[], &[1002, 4, 3, 4, 99]),
+        case("day5 negative", "1101,100,-1,4,0", &[], &[], &[1101, 100, -1, 4, 99]),
+        case("day9 quine", QUINE, &[], &crate::parse(QUINE).unwrap_or_default(), &[]),
+        case("day9 16-digit number", "1102,34915192,34915192,7,4,7,99,0", &[], &[1219070632396864], &[]),
+        case("day9 large number", "104,1125899906842624,99", &[], &[1125899906842624], &[]),
+    ];
+
+    let comparisons: [Comparison; 6] = [
+        ("equal to 8, position", "3,9,8,9,10,9,4,9,99,-1,8", |value| (value == 8) as i64),
+        ("less than 8, position", "3,9,7,9,10,9,4,9,99,-1,8", |value| (value < 8) as i64),
+        ("equal to 8, immediate", "3,3,1108,-1,8,3,4,3,99", |value| (value == 8) as i64),
+        ("less than 8, immediate", "3,3,1107,-1,8,3,4,3,99", |value| (value < 8) as i64),
+        ("jump, position", "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9", |value| (value != 0) as i64),
+        ("jump, immediate", "3,3,1105,-1,9,1101,0,0,12,4,12,99,1", |value| (value != 0) as i64),
+    ];
+    for &(name, program, expected) in &comparisons {
+        for &value in &[-3, 0, 7, 8, 9] {
+            cases.push(case(&format!("day5 {} with {}", name, value), program, &[value], &[expected(value)], &[]));
+        }
+    }
+    for &value in &[-3, 0, 7, 8, 9] {
+        let expected = 1000 + value.cmp(&8) as i64;
+        cases.push(case(&format!("day5 compared to 8 with {}", value), COMPARE_LARGE, &[value], &[expected], &[]));
+    }
+    cases
+}
+
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Mismatch {
+    pub engine: String,
+    pub case: String,
+    pub expected: String,
+    pub actual: String
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}: expected {}, got {}", self.engine, self.case, self.expected, self.actual)
+    }
+}
+
+/// Runs every case on `engine`, one `Mismatch` per case that does not behave as expected.
+pub fn check<E: Engine + ?Sized>(engine: &E, cases: &[Case]) -> Vec<Mismatch> {
+    let mut mismatches: Vec<Mismatch> = vec![];
+    for case in cases {
+        let mismatch = |expected: String, actual: String| Mismatch{
+            engine: engine.name().to_string(), case: case.name.clone(), expected, actual
+        };
+        let code = match crate::parse(case.program) {
+            Ok(code) => code,
+            Err(error) => {
+                mismatches.push(mismatch("a valid program".to_string(), error.to_string()));
+                continue;
+            },
+        };
+        let (output, memory) = match engine.execute(&code, &case.input) {
+            Ok(result) => result,
+            Err(error) => {
+                mismatches.push(mismatch(format!("output {:?}", case.output), format!("error: {}", error)));
+                continue;
+            },
+        };
+        if output != case.output {
+            mismatches.push(mismatch(format!("output {:?}", case.output), format!("{:?}", output)));
+        }
+        if !case.memory.is_empty() && memory.get(..case.memory.len()) != Some(&case.memory[..]) {
+            mismatches.push(mismatch(format!("memory {:?}", case.memory), format!("{:?}", memory)));
+        }
+    }
+    mismatches
+}
@@ -0,0 +1,221 @@
+//! Line-based debugger around `Intcode`, see `HELP` for the commands.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+use crate::disasm::{decode_with, Instruction};
+use crate::snapshot::Snapshot;
+use crate::{Intcode, Memory, Op, VmError, IO};
+
+
+pub const HELP: &str = "\
+step [n]            execute n instructions (1 by default)
+continue            run until a breakpoint, a watchpoint, input wait or halt
+break [addr]        set a breakpoint, list them without an address
+delete <addr>       remove a breakpoint
+watch <addr>        stop whenever the memory cell changes
+unwatch <addr>      remove a watchpoint
+print mem[a..b]     show memory cells a to b (exclusive), or a single one with mem[a]
+show                show iptr, relative base and the pending op
+input <v> [v ...]   queue values for the Input op
+output              show values written since the last time
+save <file>         write a snapshot of the machine and its queues
+load <file>         restore a snapshot written with save
+help                this text
+quit                leave the debugger";
+
+
+/// Why execution stopped before the requested number of steps.
+#[derive(Debug, Eq, PartialEq, Clone)]
+enum Stop {
+    Breakpoint(usize),
+    Watchpoint(usize, i64, i64),
+    NeedsInput,
+    Halted,
+}
+
+pub struct Debugger {
+    pub program: Intcode,
+    pub breakpoints: BTreeSet<usize>,
+    // watched address with the value it had when last checked
+    pub watchpoints: BTreeMap<usize, i64>,
+    pub input: IO,
+    pub output: IO
+}
+
+impl Debugger {
+    pub fn new(program: Intcode) -> Self {
+        Self{
+            program,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeMap::new(),
+            input: IO::new(false),
+            output: IO::new(false)
+        }
+    }
+
+    fn peek(&self, addr: usize) -> i64 {
+        self.program.peek(addr)
+    }
+
+    /// The pending instruction the way the disassembler prints it.
+    pub fn current(&self) -> String {
+        match decode_with(|addr| if addr < self.program.memory.len() { Some(self.peek(addr)) } else { None }, self.program.iptr) {
+            Some(instruction) => instruction.to_string(),
+            None => Instruction::Data { addr: self.program.iptr, value: self.peek(self.program.iptr) }.to_string(),
+        }
+    }
+
+    fn step_once(&mut self) -> Result<Option<Stop>, VmError> {
+        match self.program.op {
+            Op::Halt => return Ok(Some(Stop::Halted)),
+            Op::Input(_) if self.input.stream.is_empty() => return Ok(Some(Stop::NeedsInput)),
+            _ => {},
+        }
+        self.program.step(&mut self.input, &mut self.output)?;
+
+        let changed: Vec<(usize, i64, i64)> = self.watchpoints.iter()
+            .map(|(&addr, &old)| (addr, old, self.peek(addr)))
+            .filter(|(_, old, new)| old != new)
+            .collect();
+        for &(addr, _, new) in &changed {
+            self.watchpoints.insert(addr, new);
+        }
+        if let Some(&(addr, old, new)) = changed.first() {
+            return Ok(Some(Stop::Watchpoint(addr, old, new)))
+        }
+        if self.breakpoints.contains(&self.program.iptr) {
+            return Ok(Some(Stop::Breakpoint(self.program.iptr)))
+        }
+        Ok(None)
+    }
+
+    fn run_steps(&mut self, limit: Option<usize>) -> Result<String, String> {
+        let mut steps: usize = 0;
+        let stop = loop {
+            if limit == Some(steps) {
+                break None;
+            }
+            match self.step_once() {
+                Ok(None) => steps += 1,
+                Ok(Some(stop @ Stop::Breakpoint(_))) | Ok(Some(stop @ Stop::Watchpoint(_, _, _))) => {
+                    steps += 1;
+                    break Some(stop);
+                },
+                Ok(Some(stop)) => break Some(stop),
+                Err(error) => return Err(format!("error after {} steps: {}", steps, error)),
+            }
+        };
+
+        let reason = match stop {
+            None => format!("{} steps", steps),
+            Some(Stop::Breakpoint(addr)) => format!("breakpoint at {} after {} steps", addr, steps),
+            Some(Stop::Watchpoint(addr, old, new)) => format!("mem[{}] changed {} -> {} after {} steps", addr, old, new, steps),
+            Some(Stop::NeedsInput) => format!("waiting for input after {} steps", steps),
+            Some(Stop::Halted) => format!("halted after {} steps", steps),
+        };
+        Ok(format!("{}\n{}", reason, self.current()))
+    }
+
+    fn print(&self, arg: &str) -> Result<String, String> {
+        let range = arg.strip_prefix("mem[").and_then(|rest| rest.strip_suffix(']'))
+            .ok_or(format!("expected mem[a..b] or mem[a], got {:?}", arg))?;
+        let (start, end) = match range.find("..") {
+            Some(index) => (parse_addr(&range[..index])?, parse_addr(&range[index+2..])?),
+            None => {
+                let addr = parse_addr(range)?;
+                (addr, addr + 1)
+            },
+        };
+        let cells: Vec<String> = (start..end).map(|addr| format!("{:04}: {}", addr, self.peek(addr))).collect();
+        Ok(cells.join("\n"))
+    }
+
+    /// Executes one command line, the text is what should be shown to the user.
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => return Ok(String::new()),
+        };
+        let args: Vec<&str> = words.collect();
+
+        match command {
+            "step" | "s" => {
+                let count = match args.first() {
+                    Some(count) => count.parse::<usize>().map_err(|_| format!("invalid step count {:?}", count))?,
+                    None => 1,
+                };
+                self.run_steps(Some(count))
+            },
+            "continue" | "c" => self.run_steps(None),
+            "break" | "b" => match args.first() {
+                Some(addr) => {
+                    let addr = parse_addr(addr)?;
+                    self.breakpoints.insert(addr);
+                    Ok(format!("breakpoint at {}", addr))
+                },
+                None => {
+                    let addrs: Vec<String> = self.breakpoints.iter().map(|addr| addr.to_string()).collect();
+                    Ok(format!("breakpoints: {}", addrs.join(", ")))
+                },
+            },
+            "delete" | "d" => {
+                let addr = parse_addr(args.first().ok_or("delete needs an address")?)?;
+                self.breakpoints.remove(&addr);
+                Ok(format!("breakpoint at {} removed", addr))
+            },
+            "watch" | "w" => {
+                let addr = parse_addr(args.first().ok_or("watch needs an address")?)?;
+                self.watchpoints.insert(addr, self.peek(addr));
+                Ok(format!("watching mem[{}] = {}", addr, self.peek(addr)))
+            },
+            "unwatch" => {
+                let addr = parse_addr(args.first().ok_or("unwatch needs an address")?)?;
+                self.watchpoints.remove(&addr);
+                Ok(format!("mem[{}] is not watched anymore", addr))
+            },
+            "print" | "p" => self.print(&args.join("")),
+            "show" => Ok(format!(
+                "iptr: {}, base: {}, op: {:?}\n{}",
+                self.program.iptr, self.program.base, self.program.op, self.current()
+            )),
+            "input" | "i" => {
+                for arg in &args {
+                    self.input.push_input(arg.parse::<i64>().map_err(|_| format!("invalid input value {:?}", arg))?);
+                }
+                Ok(format!("{} values queued", self.input.stream.len()))
+            },
+            "output" | "o" => {
+                let values: Vec<String> = self.output.drain_outputs().iter().map(|value| value.to_string()).collect();
+                Ok(values.join(","))
+            },
+            "save" => {
+                let path = args.first().ok_or("save needs a file name")?;
+                let file = File::create(path).map_err(|error| error.to_string())?;
+                self.program.snapshot(&self.input, &self.output).save(BufWriter::new(file)).map_err(|error| error.to_string())?;
+                Ok(format!("saved to {}", path))
+            },
+            "load" => {
+                let path = args.first().ok_or("load needs a file name")?;
+                let file = File::open(path).map_err(|error| error.to_string())?;
+                let snapshot = Snapshot::load(BufReader::new(file)).map_err(|error| error.to_string())?;
+                let (program, input, output) = Intcode::from_snapshot(snapshot).map_err(|error| error.to_string())?;
+                self.program = program;
+                self.input = input;
+                self.output = output;
+                for (&addr, value) in self.watchpoints.iter_mut() {
+                    *value = self.program.peek(addr);
+                }
+                Ok(format!("loaded from {}\n{}", path, self.current()))
+            },
+            "help" | "h" => Ok(HELP.to_string()),
+            unknown => Err(format!("unknown command {:?}, try help", unknown)),
+        }
+    }
+}
+
+fn parse_addr(text: &str) -> Result<usize, String> {
+    text.trim().parse::<usize>().map_err(|_| format!("invalid address {:?}", text))
+}
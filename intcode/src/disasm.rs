@@ -0,0 +1,167 @@
+//! Turning program memory back into a readable listing.
+//!
+//! Only cells reachable from address 0 are decoded as instructions: the walk follows
+//! fall-through and every jump whose target is an immediate value. Everything else,
+//! including jump tables reached through position or relative targets, is listed as data.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::{Mode, Opcode};
+
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Param {
+    pub mode: Mode,
+    pub value: i64
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mode {
+            Mode::Position => write!(f, "[{}]", self.value),
+            Mode::Immediate => write!(f, "#{}", self.value),
+            Mode::Relative if self.value < 0 => write!(f, "[r{}]", self.value),
+            Mode::Relative => write!(f, "[r+{}]", self.value),
+        }
+    }
+}
+
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Instruction {
+    Code { addr: usize, opcode: Opcode, params: Vec<Param> },
+    Data { addr: usize, value: i64 },
+}
+
+impl Instruction {
+    pub fn addr(&self) -> usize {
+        match self {
+            Instruction::Code { addr, .. } | Instruction::Data { addr, .. } => *addr,
+        }
+    }
+
+    /// Number of memory cells the instruction occupies.
+    pub fn size(&self) -> usize {
+        match self {
+            Instruction::Code { params, .. } => params.len() + 1,
+            Instruction::Data { .. } => 1,
+        }
+    }
+
+    /// Addresses execution may continue at, as far as they are known without running the program.
+    pub fn successors(&self) -> Vec<usize> {
+        let (addr, opcode, params) = match self {
+            Instruction::Code { addr, opcode, params } => (*addr, *opcode, params),
+            Instruction::Data { .. } => return vec![],
+        };
+        let fall_through = addr + self.size();
+        let immediate_target = |param: &Param| if param.mode == Mode::Immediate && param.value >= 0 {
+            Some(param.value as usize)
+        } else {
+            None
+        };
+
+        match opcode {
+            Opcode::Halt => vec![],
+            Opcode::JumpTrue | Opcode::JumpFalse => {
+                let condition = params[0];
+                let always = condition.mode == Mode::Immediate && ((condition.value != 0) == (opcode == Opcode::JumpTrue));
+                let never = condition.mode == Mode::Immediate && !always;
+                let mut next = vec![];
+                if !always {
+                    next.push(fall_through);
+                }
+                if !never {
+                    next.extend(immediate_target(&params[1]));
+                }
+                next
+            },
+            _ => vec![fall_through],
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::Data { addr, value } => write!(f, "{:04}: DATA {}", addr, value),
+            Instruction::Code { addr, opcode, params } => {
+                write!(f, "{:04}: {}", addr, opcode.mnemonic().to_uppercase())?;
+                let (reads, writes) = if opcode.writes() {
+                    params.split_at(params.len() - 1)
+                } else {
+                    (&params[..], &[][..])
+                };
+                let reads: Vec<String> = reads.iter().map(|param| param.to_string()).collect();
+                if !reads.is_empty() {
+                    write!(f, " {}", reads.join(", "))?;
+                }
+                if let Some(target) = writes.first() {
+                    write!(f, " -> {}", target)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+
+/// Decodes the instruction at `addr`, `None` if the cell does not hold a valid complete one.
+pub fn decode(code: &[i64], addr: usize) -> Option<Instruction> {
+    decode_with(|addr| code.get(addr).cloned(), addr)
+}
+
+/// Same as `decode`, reading the cells with `fetch` instead of from a slice.
+pub fn decode_with<F: Fn(usize) -> Option<i64>>(fetch: F, addr: usize) -> Option<Instruction> {
+    let word = fetch(addr)?;
+    let opcode = Opcode::from_code(word % 100)?;
+    let mut acc = word / 100;
+    let mut params: Vec<Param> = vec![];
+    for offset in 1..=opcode.params_num() {
+        let mode = Mode::from_digit(acc % 10)?;
+        acc /= 10;
+        params.push(Param{mode, value: fetch(addr + offset)?});
+    }
+    Some(Instruction::Code { addr, opcode, params })
+}
+
+/// Addresses of every instruction reachable from address 0.
+pub fn reachable(code: &[i64]) -> BTreeSet<usize> {
+    let mut starts: BTreeSet<usize> = BTreeSet::new();
+    let mut queue: Vec<usize> = vec![0];
+    while let Some(addr) = queue.pop() {
+        if starts.contains(&addr) {
+            continue;
+        }
+        if let Some(instruction) = decode(code, addr) {
+            starts.insert(addr);
+            queue.extend(instruction.successors());
+        }
+    }
+    starts
+}
+
+/// Splits the whole program into instructions and data cells, in address order.
+///
+/// A jump into the middle of an already decoded instruction is not shown separately.
+pub fn disassemble(code: &[i64]) -> Vec<Instruction> {
+    let starts = reachable(code);
+    let mut instructions: Vec<Instruction> = vec![];
+    let mut addr: usize = 0;
+    while addr < code.len() {
+        let instruction = match decode(code, addr) {
+            Some(instruction) if starts.contains(&addr) => instruction,
+            _ => Instruction::Data { addr, value: code[addr] },
+        };
+        addr += instruction.size();
+        instructions.push(instruction);
+    }
+    instructions
+}
+
+/// One instruction per line, see `Instruction`'s `Display`.
+pub fn listing(instructions: &[Instruction]) -> String {
+    let lines: Vec<String> = instructions.iter().map(|instruction| instruction.to_string()).collect();
+    lines.join("\n")
+}
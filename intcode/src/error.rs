@@ -0,0 +1,43 @@
+use std::error::Error;
+use std::fmt;
+
+
+/// Everything that can go wrong while loading or executing an Intcode program.
+///
+/// `addr` is always the address of the instruction being decoded or executed.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum VmError {
+    BadOpcode { addr: usize, opcode: i64 },
+    BadMode { addr: usize, mode: i64 },
+    NegativeAddress { addr: usize, target: i64 },
+    MissingInput { addr: usize },
+    Parse { index: usize, token: String },
+    JumpOutOfBounds { addr: usize, target: i64 },
+    BadSnapshot { reason: String },
+    MemoryLimit { addr: usize, target: usize },
+    Overflow { addr: usize },
+    TooComplex { reason: String },
+    BadPatch { reason: String },
+    BadSchedule { reason: String },
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VmError::BadOpcode { addr, opcode } => write!(f, "invalid instruction code {} at {}", opcode, addr),
+            VmError::BadMode { addr, mode } => write!(f, "invalid mode identifier {} at {}", mode, addr),
+            VmError::NegativeAddress { addr, target } => write!(f, "negative address {} accessed at {}", target, addr),
+            VmError::MissingInput { addr } => write!(f, "no input available for instruction at {}", addr),
+            VmError::Parse { index, token } => write!(f, "can not parse token #{} {:?} as an integer", index, token),
+            VmError::JumpOutOfBounds { addr, target } => write!(f, "jump to {} at {} is out of program bounds", target, addr),
+            VmError::BadSnapshot { reason } => write!(f, "invalid snapshot: {}", reason),
+            VmError::MemoryLimit { addr, target } => write!(f, "address {} accessed at {} is beyond the memory limits", target, addr),
+            VmError::Overflow { addr } => write!(f, "value out of the i64 range at {}", addr),
+            VmError::TooComplex { reason } => write!(f, "path constraint is too complex: {}", reason),
+            VmError::BadPatch { reason } => write!(f, "invalid patch: {}", reason),
+            VmError::BadSchedule { reason } => write!(f, "invalid schedule: {}", reason),
+        }
+    }
+}
+
+impl Error for VmError {}
@@ -0,0 +1,230 @@
+//! Faster execution engine for long running programs.
+//!
+//! `Intcode` decodes the next op into an `Op` with freshly allocated vectors after every
+//! instruction. `FastIntcode` keeps flat memory and caches the decoded instruction word
+//! (opcode and modes) per address, parameters are read straight from memory when the
+//! instruction executes. Writing a cell drops its cached decode, so self-modifying
+//! programs behave exactly as in `Intcode`.
+
+use crate::{InputSource, Intcode, Memory, MemoryLimits, Mode, Opcode, OutputSink, RunState, VmError, VmResult};
+
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+struct Decoded {
+    opcode: Opcode,
+    // modes past the parameters of the opcode are never looked at
+    modes: [Mode; 3]
+}
+
+fn decode(word: i64, addr: usize) -> VmResult<Decoded> {
+    let opcode = Opcode::from_code(word % 100).ok_or(VmError::BadOpcode { addr, opcode: word % 100 })?;
+    let mut modes = [Mode::Position; 3];
+    let mut acc = word / 100;
+    for mode in modes.iter_mut().take(opcode.params_num()) {
+        *mode = Mode::from_digit(acc % 10).ok_or(VmError::BadMode { addr, mode: acc % 10 })?;
+        acc /= 10;
+    }
+    Ok(Decoded{opcode, modes})
+}
+
+
+#[derive(Debug, Clone)]
+pub struct FastIntcode {
+    pub memory: Vec<i64>,
+    pub iptr: usize,
+    pub base: i64,
+    // length of the loaded program, running past it halts as in `Intcode`
+    len: usize,
+    // highest address that can be accessed, memory is flat so it is capped by the page budget too
+    max_addr: usize,
+    decoded: Vec<Option<Decoded>>
+}
+
+impl FastIntcode {
+    pub fn new(data: &str) -> VmResult<Self> {
+        Ok(Self::from_code(crate::parse(data)?))
+    }
+
+    pub fn from_code(code: Vec<i64>) -> Self {
+        Self::with_limits(code, MemoryLimits::default())
+    }
+
+    pub fn with_limits(code: Vec<i64>, limits: MemoryLimits) -> Self {
+        Self{
+            decoded: vec![None; code.len()],
+            len: code.len(),
+            memory: code,
+            iptr: 0,
+            base: 0,
+            max_addr: limits.flat_max_addr()
+        }
+    }
+
+    /// Continues from the state of `program`, e.g. one restored from a snapshot.
+    pub fn from_intcode<M: Memory<Cell = i64>>(program: &Intcode<M>) -> VmResult<Self> {
+        let mut fast = Self::from_code(program.memory.to_vec());
+        fast.iptr = program.iptr;
+        fast.base = program.base;
+        for (addr, value) in program.memory.nonzero() {
+            if addr >= fast.len {
+                fast.write(addr, value)?;
+            }
+        }
+        Ok(fast)
+    }
+
+    /// Number of cells the program was loaded with.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn peek(&self, addr: usize) -> i64 {
+        self.memory.get(addr).cloned().unwrap_or(0)
+    }
+
+    fn read(&self, addr: usize) -> VmResult<i64> {
+        if addr > self.max_addr {
+            return Err(VmError::MemoryLimit { addr: self.iptr, target: addr })
+        }
+        Ok(self.peek(addr))
+    }
+
+    fn write(&mut self, addr: usize, value: i64) -> VmResult<()> {
+        if addr > self.max_addr {
+            return Err(VmError::MemoryLimit { addr: self.iptr, target: addr })
+        }
+        if addr >= self.memory.len() {
+            self.memory.resize(addr + 1, 0);
+        }
+        self.memory[addr] = value;
+        if let Some(decoded) = self.decoded.get_mut(addr) {
+            *decoded = None;
+        }
+        Ok(())
+    }
+
+    fn fetch(&mut self) -> VmResult<Decoded> {
+        if let Some(Some(decoded)) = self.decoded.get(self.iptr) {
+            return Ok(*decoded)
+        }
+        let decoded = decode(self.memory[self.iptr], self.iptr)?;
+        if self.decoded.len() < self.memory.len() {
+            self.decoded.resize(self.memory.len(), None);
+        }
+        self.decoded[self.iptr] = Some(decoded);
+        Ok(decoded)
+    }
+
+    fn address(&self, target: i64) -> VmResult<usize> {
+        if target < 0 {
+            return Err(VmError::NegativeAddress { addr: self.iptr, target })
+        }
+        Ok(target as usize)
+    }
+
+    /// Value of the parameter `index` (0-based) of the current instruction.
+    fn param(&self, decoded: Decoded, index: usize) -> VmResult<i64> {
+        let value = self.read(self.iptr + index + 1)?;
+        match decoded.modes[index] {
+            Mode::Position => self.read(self.address(value)?),
+            Mode::Immediate => Ok(value),
+            Mode::Relative => self.read(self.address(self.base.wrapping_add(value))?),
+        }
+    }
+
+    /// Address the parameter `index` of the current instruction points to.
+    fn target(&self, decoded: Decoded, index: usize) -> VmResult<usize> {
+        let value = self.read(self.iptr + index + 1)?;
+        match decoded.modes[index] {
+            Mode::Relative => self.address(self.base.wrapping_add(value)),
+            _ => self.address(value),
+        }
+    }
+
+    fn jump(&mut self, target: i64) -> VmResult<()> {
+        if target < 0 || target as usize >= self.len {
+            return Err(VmError::JumpOutOfBounds { addr: self.iptr, target })
+        }
+        self.iptr = target as usize;
+        Ok(())
+    }
+
+    /// Same contract as `Intcode::run`.
+    pub fn run<I: InputSource, O: OutputSink>(&mut self, input: &mut I, output: &mut O) -> VmResult<RunState> {
+        loop {
+            if let Some(state) = self.run_for(input, output, usize::MAX)? {
+                return Ok(state)
+            }
+        }
+    }
+
+    /// Like `run`, but executes at most `budget` instructions, `None` once they are used up.
+    pub fn run_for<I: InputSource, O: OutputSink>(&mut self, input: &mut I, output: &mut O, mut budget: usize) -> VmResult<Option<RunState>> {
+        while self.iptr < self.len {
+            if budget == 0 {
+                return Ok(None)
+            }
+            budget -= 1;
+            let decoded = self.fetch()?;
+            match decoded.opcode {
+                Opcode::Add => {
+                    let (value1, value2) = (self.param(decoded, 0)?, self.param(decoded, 1)?);
+                    let addr = self.target(decoded, 2)?;
+                    self.write(addr, value1.wrapping_add(value2))?;
+                    self.iptr += 4;
+                },
+                Opcode::Mult => {
+                    let (value1, value2) = (self.param(decoded, 0)?, self.param(decoded, 1)?);
+                    let addr = self.target(decoded, 2)?;
+                    self.write(addr, value1.wrapping_mul(value2))?;
+                    self.iptr += 4;
+                },
+                Opcode::Input => {
+                    let addr = self.target(decoded, 0)?;
+                    let value = match input.read_input() {
+                        Some(value) => value,
+                        None => return Ok(Some(RunState::NeedsInput)),
+                    };
+                    self.write(addr, value)?;
+                    self.iptr += 2;
+                    if input.blocking() {
+                        return Ok(Some(RunState::Yielded))
+                    }
+                },
+                Opcode::Output => {
+                    let value = self.param(decoded, 0)?;
+                    self.iptr += 2;
+                    if output.blocking() {
+                        return Ok(Some(RunState::Output(value)))
+                    }
+                    output.write_output(value);
+                },
+                Opcode::JumpTrue | Opcode::JumpFalse => {
+                    let (value, target) = (self.param(decoded, 0)?, self.param(decoded, 1)?);
+                    if (value != 0) == (decoded.opcode == Opcode::JumpTrue) {
+                        self.jump(target)?;
+                    } else {
+                        self.iptr += 3;
+                    }
+                },
+                Opcode::Less | Opcode::Equals => {
+                    let (value1, value2) = (self.param(decoded, 0)?, self.param(decoded, 1)?);
+                    let addr = self.target(decoded, 2)?;
+                    let result = if decoded.opcode == Opcode::Less { value1 < value2 } else { value1 == value2 };
+                    self.write(addr, result as i64)?;
+                    self.iptr += 4;
+                },
+                Opcode::AdjustBase => {
+                    self.base = self.base.wrapping_add(self.param(decoded, 0)?);
+                    self.iptr += 2;
+                },
+                Opcode::Halt => break,
+            }
+        }
+        Ok(Some(RunState::Halted))
+    }
+}
@@ -0,0 +1,223 @@
+//! Random program generation and the invariants every run has to satisfy.
+//!
+//! Programs are mostly well-formed instructions with random modes and addresses close to
+//! the program, mixed with garbage cells, out of range addresses and invalid modes. Each one
+//! runs under an instruction budget and is checked for:
+//! - no panics, every failure is a `VmError` raised while running,
+//! - the same result when run twice,
+//! - the same result when interrupted by a snapshot that goes through JSON and back,
+//! - the same result on `FastIntcode` as on the reference `Intcode`.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::fast::FastIntcode;
+use crate::snapshot::Snapshot;
+use crate::{Intcode, Memory, Op, PagedMemory, RunState, VmError, VmResult, IO, OPCODES};
+
+
+/// xorshift64*, good enough to spread programs around and reproducible from a seed.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // one splitmix64 round, so that neighbouring seeds give unrelated sequences
+        let mut state = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        state ^= state >> 31;
+        // the state must never be zero
+        Self{state: state.max(1)}
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Uniform in `0..n`, `n` has to be positive.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    pub fn range(&mut self, low: i64, high: i64) -> i64 {
+        low + self.below((high - low) as u64) as i64
+    }
+
+    /// True with the probability `percent` / 100.
+    pub fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+}
+
+
+fn random_value(rng: &mut Rng, len: usize) -> i64 {
+    match rng.below(20) {
+        0 => rng.range(-10, 0),
+        1 => i64::MAX - rng.range(0, 3),
+        2 => 1 << 50,
+        3 => rng.range(1_000, 100_000),
+        _ => rng.range(0, len as i64 + 8),
+    }
+}
+
+pub fn random_program(rng: &mut Rng, len: usize) -> Vec<i64> {
+    let mut code: Vec<i64> = vec![];
+    while code.len() < len {
+        if rng.chance(15) {
+            code.push(random_value(rng, len));
+            continue;
+        }
+        let opcode = OPCODES[rng.below(OPCODES.len() as u64) as usize];
+        let mut word = opcode.code();
+        let mut scale = 100;
+        for _ in 0..opcode.params_num() {
+            // mode 3 is invalid, 0 to 2 are position, immediate and relative
+            word += scale * if rng.chance(3) { 3 } else { rng.range(0, 3) };
+            scale *= 10;
+        }
+        code.push(word);
+        for _ in 0..opcode.params_num() {
+            code.push(random_value(rng, len));
+        }
+    }
+    code
+}
+
+pub fn random_inputs(rng: &mut Rng) -> Vec<i64> {
+    let count = rng.below(6);
+    (0..count).map(|_| rng.range(-10, 100)).collect()
+}
+
+/// The program, its inputs and the snapshot step for `check`, all made from `seed`.
+///
+/// `budget` has to be positive.
+pub fn random_case(seed: u64, budget: usize) -> (Vec<i64>, Vec<i64>, usize) {
+    let mut rng = Rng::new(seed);
+    let len = 4 + rng.below(60) as usize;
+    let code = random_program(&mut rng, len);
+    let inputs = random_inputs(&mut rng);
+    let split = rng.below(budget as u64) as usize;
+    (code, inputs, split)
+}
+
+
+/// Where a run ended, `result` is `None` if the budget ran out first.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Run {
+    pub result: Option<VmResult<RunState>>,
+    pub output: Vec<i64>,
+    // program length and the cells that are not zero
+    pub memory: (usize, Vec<(usize, i64)>)
+}
+
+/// Executes at most `budget` instructions of `vm`, `None` once they are used up.
+pub fn step_for(vm: &mut Intcode, input: &mut IO, output: &mut IO, budget: usize) -> Option<VmResult<RunState>> {
+    for _ in 0..budget {
+        match vm.op {
+            Op::Halt => return Some(Ok(RunState::Halted)),
+            Op::Input(_) if input.stream.is_empty() => return Some(Ok(RunState::NeedsInput)),
+            _ => {},
+        }
+        if let Err(error) = vm.step(input, output) {
+            return Some(Err(error))
+        }
+    }
+    None
+}
+
+fn contents<M: Memory<Cell = i64>>(memory: &M) -> (usize, Vec<(usize, i64)>) {
+    (memory.len(), memory.nonzero())
+}
+
+fn queues(inputs: &[i64]) -> (IO, IO) {
+    let mut input = IO::new(false);
+    input.extend_inputs(inputs.iter().cloned());
+    (input, IO::new(false))
+}
+
+fn reference(code: &[i64], inputs: &[i64], budget: usize) -> Run {
+    let (mut input, mut output) = queues(inputs);
+    let (result, memory) = match Intcode::with_memory(PagedMemory::new(code)) {
+        Ok(mut vm) => (step_for(&mut vm, &mut input, &mut output, budget), contents(&vm.memory)),
+        Err(error) => (Some(Err(error)), contents(&code.to_vec())),
+    };
+    Run{result, output: output.drain_outputs(), memory}
+}
+
+/// Same as `reference`, with a trip through a JSON snapshot after `split` instructions.
+fn interrupted(code: &[i64], inputs: &[i64], budget: usize, split: usize) -> Result<Run, String> {
+    let (mut input, mut output) = queues(inputs);
+    let mut vm = match Intcode::with_memory(PagedMemory::new(code)) {
+        Ok(vm) => vm,
+        Err(error) => return Ok(Run{result: Some(Err(error)), output: vec![], memory: contents(&code.to_vec())}),
+    };
+    if let Some(result) = step_for(&mut vm, &mut input, &mut output, split) {
+        return Ok(Run{result: Some(result), output: output.drain_outputs(), memory: contents(&vm.memory)})
+    }
+
+    let mut json: Vec<u8> = vec![];
+    vm.snapshot(&input, &output).save(&mut json).map_err(|error| format!("snapshot not saved: {}", error))?;
+    let snapshot = Snapshot::load(&json[..]).map_err(|error| format!("snapshot not loaded: {}", error))?;
+    let (mut vm, mut input, mut output) = Intcode::from_snapshot(snapshot).map_err(|error| format!("snapshot not restored: {}", error))?;
+
+    let result = step_for(&mut vm, &mut input, &mut output, budget - split);
+    Ok(Run{result, output: output.drain_outputs(), memory: contents(&vm.memory)})
+}
+
+fn fast(code: &[i64], inputs: &[i64], budget: usize) -> Run {
+    let (mut input, mut output) = queues(inputs);
+    let mut vm = FastIntcode::from_code(code.to_vec());
+    let result = match vm.run_for(&mut input, &mut output, budget) {
+        Ok(Some(state)) => Some(Ok(state)),
+        Ok(None) => None,
+        Err(error) => Some(Err(error)),
+    };
+    Run{result, output: output.drain_outputs(), memory: (vm.len(), vm.memory.nonzero())}
+}
+
+fn guarded<T, F: FnOnce() -> T>(engine: &str, run: F) -> Result<T, String> {
+    catch_unwind(AssertUnwindSafe(run)).map_err(|panic| {
+        let message = panic.downcast_ref::<&str>().map(|text| text.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        format!("{} panicked: {}", engine, message)
+    })
+}
+
+/// Checks every invariant on one program, the description of the first broken one on failure.
+///
+/// `split` is where the snapshot is taken, it has to be below `budget`.
+pub fn check(code: &[i64], inputs: &[i64], budget: usize, split: usize) -> Result<(), String> {
+    if split >= budget {
+        return Err(format!("snapshot step {} is not below the budget of {}", split, budget))
+    }
+    let expected = guarded("Intcode", || reference(code, inputs, budget))?;
+    if let Some(Err(error @ VmError::Parse { .. })) | Some(Err(error @ VmError::BadSnapshot { .. })) = &expected.result {
+        return Err(format!("error that can not happen while running: {}", error))
+    }
+
+    let again = guarded("Intcode", || reference(code, inputs, budget))?;
+    if again != expected {
+        return Err(format!("not deterministic: {:?} then {:?}", expected, again))
+    }
+
+    let restored = guarded("snapshot", || interrupted(code, inputs, budget, split))??;
+    if restored != expected {
+        return Err(format!("snapshot after {} steps changes the run: {:?} instead of {:?}", split, restored, expected))
+    }
+
+    let actual = guarded("FastIntcode", || fast(code, inputs, budget))?;
+    // runs cut short by the budget may stop at different points, and the flat memory of
+    // FastIntcode hits its limit far earlier, so only complete runs within the limits are compared
+    let limited = |run: &Run| run.result.is_none() || matches!(run.result, Some(Err(VmError::MemoryLimit { .. })));
+    let comparable = !limited(&expected) && !limited(&actual);
+    if comparable && actual != expected || !comparable && !actual.output.starts_with(&expected.output) && !expected.output.starts_with(&actual.output) {
+        return Err(format!("FastIntcode differs: {:?} instead of {:?}", actual, expected))
+    }
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! Where `Input` ops take their values from and where `Output` ops put them.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Stdin, Stdout, Write};
+use std::sync::mpsc::{Receiver, Sender};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ascii::Chunk;
+
+
+/// `C` is the cell type of the machine, see `cell`.
+pub trait InputSource<C = i64> {
+    /// Next value for an `Input` op, `None` if there is nothing to read right now.
+    fn read_input(&mut self) -> Option<C>;
+
+    /// Whether `Intcode::run` returns `Yielded` after every consumed value.
+    fn blocking(&self) -> bool {
+        false
+    }
+}
+
+pub trait OutputSink<C = i64> {
+    fn write_output(&mut self, value: C);
+
+    /// Whether `Intcode::run` returns `Output` with the value instead of writing it.
+    fn blocking(&self) -> bool {
+        false
+    }
+}
+
+
+/// FIFO stream of values: `Input` reads from the front, `Output` appends to the back.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct IO {
+    pub stream: VecDeque<i64>,
+    pub blocking: bool
+}
+
+impl IO {
+    pub fn new(blocking: bool) -> Self {
+        Self{
+            stream: VecDeque::new(),
+            blocking
+        }
+    }
+
+    pub fn push_input(&mut self, value: i64) {
+        self.stream.push_back(value);
+    }
+
+    pub fn extend_inputs<I: IntoIterator<Item = i64>>(&mut self, values: I) {
+        self.stream.extend(values);
+    }
+
+    /// Takes every value from the stream, oldest first.
+    pub fn drain_outputs(&mut self) -> Vec<i64> {
+        self.stream.drain(..).collect()
+    }
+
+    /// Takes complete `(x, y, z)` triples from the stream, oldest first.
+    ///
+    /// A trailing incomplete triple is left in the stream until the rest of it is produced.
+    pub fn take_output_triples(&mut self) -> Vec<(i64, i64, i64)> {
+        let complete = self.stream.len() - self.stream.len() % 3;
+        let values: Vec<i64> = self.stream.drain(..complete).collect();
+        values.chunks(3).map(|chunk| (chunk[0], chunk[1], chunk[2])).collect()
+    }
+}
+
+impl InputSource for IO {
+    fn read_input(&mut self) -> Option<i64> {
+        self.stream.pop_front()
+    }
+
+    fn blocking(&self) -> bool {
+        self.blocking
+    }
+}
+
+impl OutputSink for IO {
+    fn write_output(&mut self, value: i64) {
+        self.stream.push_back(value);
+    }
+
+    fn blocking(&self) -> bool {
+        self.blocking
+    }
+}
+
+
+impl<C> InputSource<C> for VecDeque<C> {
+    fn read_input(&mut self) -> Option<C> {
+        self.pop_front()
+    }
+}
+
+impl<C> OutputSink<C> for VecDeque<C> {
+    fn write_output(&mut self, value: C) {
+        self.push_back(value);
+    }
+}
+
+impl<C> OutputSink<C> for Vec<C> {
+    fn write_output(&mut self, value: C) {
+        self.push(value);
+    }
+}
+
+
+impl<C, F: FnMut() -> Option<C>> InputSource<C> for F {
+    fn read_input(&mut self) -> Option<C> {
+        self()
+    }
+}
+
+impl<C, F: FnMut(C)> OutputSink<C> for F {
+    fn write_output(&mut self, value: C) {
+        self(value)
+    }
+}
+
+
+/// Blocks until a value arrives, `None` once every sender is gone.
+impl<C> InputSource<C> for Receiver<C> {
+    fn read_input(&mut self) -> Option<C> {
+        self.recv().ok()
+    }
+}
+
+impl<C> OutputSink<C> for Sender<C> {
+    fn write_output(&mut self, value: C) {
+        // the receiving side may have halted already, nobody is going to read the value then
+        let _ = self.send(value);
+    }
+}
+
+
+/// Interactive prompt, asks for a value until it gets a number. `None` on end of input.
+impl InputSource for Stdin {
+    fn read_input(&mut self) -> Option<i64> {
+        loop {
+            let mut line = String::new();
+            println!();
+            print!("$ ");
+            io::stdout().flush().ok()?;
+            if self.lock().read_line(&mut line).ok()? == 0 {
+                return None
+            }
+            match line.trim().parse() {
+                Ok(value) => return Some(value),
+                Err(_) => println!("not a number: {:?}", line.trim()),
+            }
+        }
+    }
+}
+
+impl OutputSink for Stdout {
+    fn write_output(&mut self, value: i64) {
+        println!("> {:?}", value);
+    }
+}
+
+
+/// Feeds text to the program as ASCII codes, one character per `Input` op.
+#[derive(Debug, Default)]
+pub struct AsciiInput {
+    pending: VecDeque<i64>
+}
+
+impl AsciiInput {
+    pub fn new(text: &str) -> Self {
+        let mut input = Self::default();
+        input.push_str(text);
+        input
+    }
+
+    pub fn push_str(&mut self, text: &str) {
+        self.pending.extend(text.bytes().map(i64::from));
+    }
+
+    /// Queues a command, the program gets the line followed by a newline.
+    pub fn push_line(&mut self, line: &str) {
+        self.pending.extend(crate::ascii::encode_line(line));
+    }
+}
+
+impl InputSource for AsciiInput {
+    fn read_input(&mut self) -> Option<i64> {
+        self.pending.pop_front()
+    }
+}
+
+/// Collects the program output as text, values out of the ASCII range are kept as numbers.
+#[derive(Debug, Default)]
+pub struct AsciiOutput {
+    // in output order, e.g. a map followed by the puzzle answer
+    pub chunks: Vec<Chunk>
+}
+
+impl OutputSink for AsciiOutput {
+    fn write_output(&mut self, value: i64) {
+        match (crate::ascii::to_char(value), self.chunks.last_mut()) {
+            (Some(symbol), Some(Chunk::Text(text))) => text.push(symbol),
+            (Some(symbol), _) => self.chunks.push(Chunk::Text(symbol.to_string())),
+            (None, _) => self.chunks.push(Chunk::Raw(value)),
+        }
+    }
+}
@@ -0,0 +1,358 @@
+//! Intcode virtual machine shared by every Intcode puzzle of Advent of Code 2019.
+//!
+//! The instruction set is the complete one from day 9: position, immediate and
+//! relative parameter modes, plus the `AdjustBase` instruction.
+
+use serde::{Deserialize, Serialize};
+
+pub mod aot;
+pub mod ascii;
+pub mod asm;
+pub mod cell;
+pub mod cfg;
+pub mod channel;
+pub mod conformance;
+pub mod debugger;
+pub mod disasm;
+pub mod fast;
+pub mod fuzz;
+mod error;
+mod io;
+mod limits;
+mod memory;
+pub mod network;
+mod opcode;
+pub mod patch;
+pub mod profile;
+pub mod scheduler;
+pub mod snapshot;
+pub mod symbolic;
+pub mod trace;
+
+pub use cell::Cell;
+pub use error::VmError;
+pub use io::{AsciiInput, AsciiOutput, InputSource, OutputSink, IO};
+pub use limits::{CancelToken, Limit, Limits};
+pub use memory::{LimitExceeded, Memory, MemoryLimits, PagedMemory, PatchedMemory, MAX_ADDR, PAGE_SIZE};
+pub use opcode::{Mode, Opcode, OPCODES};
+pub use trace::Tracer;
+
+pub type VmResult<T> = Result<T, VmError>;
+
+
+#[repr(usize)]
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Op<C = i64> {
+    Add(C, C, usize), // 1
+    Mult(C, C, usize), // 2
+    Input(usize), // 3
+    Output(C), // 4
+    JumpTrue(C, usize), // 5
+    JumpFalse(C, usize), // 6
+    Less(C, C, usize), // 7
+    Equals(C, C, usize), // 8
+    AdjustBase(C), // 9
+    Halt, // 99
+}
+
+impl<C> Op<C> {
+    /// Number of parameters following the opcode in memory.
+    pub fn params_num(&self) -> usize {
+        match self {
+            Op::Add(_, _ ,_) | Op::Mult(_, _, _) | Op::Less(_, _, _) | Op::Equals(_, _, _) => 3,
+            Op::JumpTrue(_, _) | Op::JumpFalse(_, _) => 2,
+            Op::Input(_) | Op::Output(_) | Op::AdjustBase(_) => 1,
+            Op::Halt => 0
+        }
+    }
+
+    /// Memory cell the op writes its result to.
+    pub fn write_addr(&self) -> Option<usize> {
+        match *self {
+            Op::Add(_, _, addr) | Op::Mult(_, _, addr) | Op::Less(_, _, addr) | Op::Equals(_, _, addr) => Some(addr),
+            Op::Input(addr) => Some(addr),
+            _ => None
+        }
+    }
+}
+
+/// Why `Intcode::run` gave control back to the caller.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum RunState<C = i64> {
+    Halted,
+    // the pending op is `Input`, but the input stream is empty
+    NeedsInput,
+    // produced by a blocking output stream instead of pushing the value into it
+    Output(C),
+    // a value was consumed from a blocking input stream
+    Yielded,
+    // stopped by `run_with_limits` before the pending op, running again continues from it
+    Limited(Limit),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "M: Serialize, M::Cell: Serialize",
+    deserialize = "M: Deserialize<'de>, M::Cell: Deserialize<'de>"
+))]
+pub struct Intcode<M: Memory = PagedMemory> {
+    pub memory: M,
+    pub iptr: usize,
+    pub op: Op<M::Cell>,
+    pub base: i64
+}
+
+
+/// Parses the comma-separated program text into memory cells.
+pub fn parse(data: &str) -> VmResult<Vec<i64>> {
+    let mut code: Vec<i64> = vec![];
+    for (index, item) in data.trim().split(',').enumerate() {
+        let value = item.trim().parse::<i64>().map_err(|_| VmError::Parse { index, token: item.to_string() })?;
+        code.push(value);
+    }
+    Ok(code)
+}
+
+fn address(iptr: usize, target: i64) -> VmResult<usize> {
+    if target < 0 {
+        return Err(VmError::NegativeAddress { addr: iptr, target })
+    }
+    Ok(target as usize)
+}
+
+/// The value of a cell used as an opcode, address or base.
+fn small<C: Cell>(iptr: usize, value: &C) -> VmResult<i64> {
+    value.to_i64().ok_or(VmError::Overflow { addr: iptr })
+}
+
+/// `base + value`, wrapping around for `i64` cells like any other sum.
+fn offset<C: Cell>(iptr: usize, base: i64, value: &C) -> VmResult<i64> {
+    small(iptr, &C::from_i64(base).try_add(value).ok_or(VmError::Overflow { addr: iptr })?)
+}
+
+
+impl Intcode {
+    pub fn new(data: &str) -> VmResult<Self> {
+        Self::with_memory(PagedMemory::new(&parse(data)?))
+    }
+}
+
+impl<M: Memory> Intcode<M> {
+    /// Starts the program already loaded into `memory` from address 0.
+    pub fn with_memory(memory: M) -> VmResult<Self> {
+        Self::resume(memory, 0, 0)
+    }
+
+    /// Continues a program stopped at `iptr` with relative base `base`, e.g. by another engine.
+    pub fn resume(memory: M, iptr: usize, base: i64) -> VmResult<Self> {
+        let op = if iptr >= memory.len() {
+            Op::Halt
+        } else {
+            Self::parse_op(&memory, iptr, base)?
+        };
+        Ok(Self{memory, iptr, op, base})
+    }
+
+    fn parse_op(memory: &M, iptr: usize, base: i64) -> VmResult<Op<M::Cell>> {
+        let read = |addr: usize| -> VmResult<M::Cell> {
+            memory.read(addr).map_err(|limit| VmError::MemoryLimit { addr: iptr, target: limit.target })
+        };
+
+        let word = small(iptr, &read(iptr)?)?;
+        let instruction = Opcode::from_code(word % 100)
+            .ok_or(VmError::BadOpcode { addr: iptr, opcode: word % 100 })?;
+        let mut acc = word / 100;
+        let mut modes: Vec<Mode> = vec![];
+        let num: usize = instruction.params_num();
+
+        for _ in 0..num {
+            modes.push(Mode::from_digit(acc % 10).ok_or(VmError::BadMode { addr: iptr, mode: acc % 10 })?);
+            acc /= 10;
+        }
+
+        // the last instruction may be truncated, missing params are read as zeroes
+        let mut params: Vec<M::Cell> = vec![];
+        for (index, &mode) in modes.iter().enumerate() {
+            let value = read(iptr + index + 1)?;
+            let param: M::Cell = match mode {
+                Mode::Position => read(address(iptr, small(iptr, &value)?)?)?,
+                Mode::Immediate => value,
+                Mode::Relative => read(address(iptr, offset(iptr, base, &value)?)?)?,
+            };
+            params.push(param);
+        }
+
+        // FIXME: this is some sort of a hack, I do not like it, but normal flow with "params" doesn't work
+        let write_addr = || -> VmResult<usize> {
+            if modes[num-1] == Mode::Relative {
+                address(iptr, offset(iptr, base, &read(iptr+num)?)?)
+            } else {
+                address(iptr, small(iptr, &read(iptr+num)?)?)
+            }
+        };
+        let param = |index: usize| params[index].clone();
+        let jump_target = || -> VmResult<usize> { Ok(small(iptr, &params[1])? as usize) };
+
+        Ok(match instruction {
+            Opcode::Add => Op::Add(param(0), param(1), write_addr()?),
+            Opcode::Mult => Op::Mult(param(0), param(1), write_addr()?),
+            Opcode::Input => Op::Input(write_addr()?),
+            Opcode::Output => Op::Output(param(0)),
+            Opcode::JumpTrue => Op::JumpTrue(param(0), jump_target()?),
+            Opcode::JumpFalse => Op::JumpFalse(param(0), jump_target()?),
+            Opcode::Less => Op::Less(param(0), param(1), write_addr()?),
+            Opcode::Equals => Op::Equals(param(0), param(1), write_addr()?),
+            Opcode::AdjustBase => Op::AdjustBase(param(0)),
+            Opcode::Halt => Op::Halt,
+        })
+    }
+
+    fn save(&mut self, result: M::Cell, addr: usize) -> VmResult<()> {
+        let iptr = self.iptr;
+        self.memory.write(addr, result).map_err(|limit| VmError::MemoryLimit { addr: iptr, target: limit.target })
+    }
+
+    /// Value of a memory cell, zero for cells out of the memory limits.
+    pub fn peek(&self, addr: usize) -> M::Cell {
+        self.memory.read(addr).unwrap_or_else(|_| M::Cell::from_i64(0))
+    }
+
+    /// Overwrites a memory cell before (or between) runs.
+    ///
+    /// The pending op is decoded again, as it may have read the old value.
+    pub fn poke(&mut self, addr: usize, value: M::Cell) -> VmResult<()> {
+        self.save(value, addr)?;
+        self.op = Self::parse_op(&self.memory, self.iptr, self.base)?;
+        Ok(())
+    }
+
+    fn finished(&self) -> bool {
+        self.iptr >= self.memory.len()
+    }
+
+    fn next(&mut self, addr: Option<usize>) -> VmResult<()> {
+        let addr = match addr {
+            Some(value) => value,
+            None => self.iptr + self.op.params_num() + 1, // adding 1 as op itself takes one place in code along with params
+        };
+
+        self.iptr = addr;
+        if self.finished() {
+            // running past the end of the program is the same as halting
+            self.op = Op::Halt;
+            return Ok(())
+        }
+        self.op = Self::parse_op(&self.memory, self.iptr, self.base)?;
+        Ok(())
+    }
+
+    /// Executes the pending op and decodes the next one. Does nothing once halted.
+    pub fn step<I, O>(&mut self, input: &mut I, output: &mut O) -> VmResult<()>
+    where I: InputSource<M::Cell>, O: OutputSink<M::Cell> {
+        let mut next_addr: Option<usize> = None;
+        let overflow = VmError::Overflow { addr: self.iptr };
+        match self.op.clone() {
+            Op::Add(value1, value2, addr) => self.save(value1.try_add(&value2).ok_or(overflow)?, addr)?,
+
+            Op::Mult(value1, value2, addr) => self.save(value1.try_mul(&value2).ok_or(overflow)?, addr)?,
+
+            Op::Input(addr) => {
+                let value: M::Cell = input.read_input().ok_or(VmError::MissingInput { addr: self.iptr })?;
+                self.save(value, addr)?;
+            },
+
+            Op::Output(value) => output.write_output(value),
+
+            Op::JumpTrue(value, addr) => if !value.is_zero() { next_addr = Some(addr) },
+
+            Op::JumpFalse(value, addr) => if value.is_zero() { next_addr = Some(addr) },
+
+            Op::Less(value1, value2, addr) => {
+                let result = if value1 < value2 { 1 } else { 0 };
+                self.save(M::Cell::from_i64(result), addr)?;
+            },
+
+            Op::Equals(value1, value2, addr) => {
+                let result = if value1 == value2 { 1 } else { 0 };
+                self.save(M::Cell::from_i64(result), addr)?;
+            },
+
+            Op::AdjustBase(value) => self.base = offset(self.iptr, self.base, &value)?,
+
+            Op::Halt => return Ok(()),
+        }
+
+        if let Some(addr) = next_addr {
+            // negative targets wrap around to huge addresses, casting back restores them for the report
+            if addr >= self.memory.len() {
+                return Err(VmError::JumpOutOfBounds { addr: self.iptr, target: addr as i64 })
+            }
+        }
+        self.next(next_addr)
+    }
+
+    /// Runs the program until it halts or has to hand control back to the caller.
+    ///
+    /// Running again after `NeedsInput` retries the same `Input` op,
+    /// so the caller only has to push a value and call `run` once more.
+    pub fn run<I, O>(&mut self, input: &mut I, output: &mut O) -> VmResult<RunState<M::Cell>>
+    where I: InputSource<M::Cell>, O: OutputSink<M::Cell> {
+        self.run_traced(input, output, &mut ())
+    }
+
+    /// Same as `run`, reporting every executed instruction to `tracer`.
+    pub fn run_traced<I, O, T>(&mut self, input: &mut I, output: &mut O, tracer: &mut T) -> VmResult<RunState<M::Cell>>
+    where I: InputSource<M::Cell>, O: OutputSink<M::Cell>, T: Tracer<M::Cell> {
+        self.run_limited_traced(input, output, tracer, &Limits::default())
+    }
+
+    /// Same as `run`, but returns `Limited` as soon as one of `limits` is reached.
+    pub fn run_with_limits<I, O>(&mut self, input: &mut I, output: &mut O, limits: &Limits) -> VmResult<RunState<M::Cell>>
+    where I: InputSource<M::Cell>, O: OutputSink<M::Cell> {
+        self.run_limited_traced(input, output, &mut (), limits)
+    }
+
+    fn run_limited_traced<I, O, T>(&mut self, input: &mut I, output: &mut O, tracer: &mut T, limits: &Limits) -> VmResult<RunState<M::Cell>>
+    where I: InputSource<M::Cell>, O: OutputSink<M::Cell>, T: Tracer<M::Cell> {
+        let mut steps: u64 = 0;
+        while !self.finished() {
+            if self.op != Op::Halt {
+                if let Some(limit) = limits.check(steps, self.op.write_addr()) {
+                    return Ok(RunState::Limited(limit))
+                }
+                steps += 1;
+            }
+            match self.op {
+                Op::Input(_) => {
+                    tracer.before_step(self);
+                    match self.step(input, output) {
+                        // nothing has been changed yet, the op is simply executed again next time
+                        Err(VmError::MissingInput { .. }) => return Ok(RunState::NeedsInput),
+                        result => result?,
+                    }
+                    tracer.after_step(self);
+                    if input.blocking() {
+                        return Ok(RunState::Yielded)
+                    }
+                },
+
+                Op::Output(ref value) if output.blocking() => {
+                    let value = value.clone();
+                    tracer.before_step(self);
+                    self.next(None)?;
+                    tracer.after_step(self);
+                    return Ok(RunState::Output(value))
+                },
+
+                Op::Halt => break,
+
+                _ => {
+                    tracer.before_step(self);
+                    self.step(input, output)?;
+                    tracer.after_step(self);
+                },
+            }
+        }
+        Ok(RunState::Halted)
+    }
+}
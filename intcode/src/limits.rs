@@ -0,0 +1,71 @@
+//! Bounds for a single `Intcode::run_with_limits` call.
+//!
+//! Every check happens before the pending op executes, so a machine stopped by a limit
+//! is left exactly at an instruction boundary and simply continues when run again.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+
+/// Which limit stopped the machine.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Limit {
+    Steps,
+    Memory,
+    Deadline,
+    Cancelled,
+}
+
+/// Shared flag to stop machines running on other threads, clones refer to the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Everything is unlimited by default.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    // instructions executed by one call
+    pub max_steps: Option<u64>,
+    // memory cells, a write to `max_memory` or above stops the machine before it happens
+    pub max_memory: Option<usize>,
+    pub deadline: Option<Instant>,
+    pub cancel: Option<CancelToken>
+}
+
+impl Limits {
+    /// The limit reached before executing a step, `steps` being the number executed so far.
+    pub(crate) fn check(&self, steps: u64, write: Option<usize>) -> Option<Limit> {
+        if self.max_steps.is_some_and(|max| steps >= max) {
+            return Some(Limit::Steps)
+        }
+        if let (Some(max), Some(addr)) = (self.max_memory, write) {
+            if addr >= max {
+                return Some(Limit::Memory)
+            }
+        }
+        if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Some(Limit::Cancelled)
+        }
+        // asking for the time is much slower than an instruction, so only do it once in a while
+        if steps.is_multiple_of(1024) && self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Some(Limit::Deadline)
+        }
+        None
+    }
+}
@@ -0,0 +1,294 @@
+//! Memory models for the VM.
+//!
+//! Intcode memory is conceptually infinite and zero-filled. `PagedMemory` only allocates
+//! the fixed-size pages that have actually been written, so a single write far away costs
+//! one page instead of growing a vector up to that address.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cell::Cell;
+
+
+pub const PAGE_SIZE: usize = 1024;
+
+/// Highest address a program may use by default, whichever engine runs it.
+pub const MAX_ADDR: usize = 1 << 48;
+
+/// The access went past the configured limits of the memory.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct LimitExceeded {
+    pub target: usize
+}
+
+pub trait Memory {
+    /// `i64` for everything but a `PagedMemory` of other cells.
+    type Cell: Cell;
+
+    /// Value of the cell, cells that have never been written are zero.
+    fn read(&self, addr: usize) -> Result<Self::Cell, LimitExceeded>;
+
+    fn write(&mut self, addr: usize, value: Self::Cell) -> Result<(), LimitExceeded>;
+
+    /// Number of cells the program was loaded with, running past them halts.
+    ///
+    /// Cells written past the program do not count, see `nonzero` for those.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The program cells `0..len()`, e.g. for the disassembler.
+    fn to_vec(&self) -> Vec<Self::Cell> {
+        (0..self.len()).map(|addr| self.read(addr).unwrap_or_else(|_| Self::Cell::from_i64(0))).collect()
+    }
+
+    /// Every cell holding something else than zero, in address order.
+    ///
+    /// Unlike `to_vec` this includes the cells written past the program, and it stays small
+    /// when a program writes far away from its code.
+    fn nonzero(&self) -> Vec<(usize, Self::Cell)> {
+        (0..self.len())
+            .filter_map(|addr| self.read(addr).ok().filter(|value| !value.is_zero()).map(|value| (addr, value)))
+            .collect()
+    }
+}
+
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct MemoryLimits {
+    // highest address that can be read or written
+    pub max_addr: usize,
+    // how many pages can be allocated at most
+    pub max_pages: usize
+}
+
+impl MemoryLimits {
+    /// Highest address of a flat memory, which has to hold every cell up to it.
+    pub fn flat_max_addr(&self) -> usize {
+        self.max_addr.min(self.max_pages * PAGE_SIZE)
+    }
+}
+
+impl Default for MemoryLimits {
+    fn default() -> Self {
+        Self{
+            max_addr: MAX_ADDR,
+            // 64 MiB worth of cells
+            max_pages: 8192
+        }
+    }
+}
+
+
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+// reading and writing index into the pages unchecked, so a loaded memory is checked first
+#[serde(try_from = "RawPagedMemory<C>", bound(deserialize = "C: Deserialize<'de>"))]
+pub struct PagedMemory<C: Cell = i64> {
+    pages: BTreeMap<usize, Vec<C>>,
+    // length of the loaded program, writes never change it
+    len: usize,
+    pub limits: MemoryLimits
+}
+
+/// `PagedMemory` as it is stored, before the checks.
+#[derive(Deserialize)]
+struct RawPagedMemory<C> {
+    pages: BTreeMap<usize, Vec<C>>,
+    len: usize,
+    limits: MemoryLimits
+}
+
+/// Fails on memory that `PagedMemory::with_limits` and later writes could not have made.
+impl<C: Cell> TryFrom<RawPagedMemory<C>> for PagedMemory<C> {
+    // becomes the reason of a `VmError::BadSnapshot` when loading a snapshot
+    type Error = String;
+
+    fn try_from(raw: RawPagedMemory<C>) -> Result<Self, String> {
+        let RawPagedMemory{pages, len, limits} = raw;
+        // the program is loaded regardless of the limits, only the pages past it count
+        let program_pages = len / PAGE_SIZE + usize::from(len % PAGE_SIZE != 0);
+        if let Some(index) = (0..program_pages).find(|index| !pages.contains_key(index)) {
+            return Err(format!("page {} of the {} cells long program is missing", index, len))
+        }
+        if pages.len() > program_pages.max(limits.max_pages) {
+            return Err(format!("{} pages are allocated, the limit is {}", pages.len(), limits.max_pages))
+        }
+        for (&index, page) in &pages {
+            if page.len() != PAGE_SIZE {
+                return Err(format!("page {} has {} cells instead of {}", index, page.len(), PAGE_SIZE))
+            }
+            let beyond_limits = index.checked_mul(PAGE_SIZE).is_none_or(|start| start > limits.max_addr);
+            if index >= program_pages && beyond_limits {
+                return Err(format!("page {} lies beyond the highest address {}", index, limits.max_addr))
+            }
+        }
+        Ok(Self{pages, len, limits})
+    }
+}
+
+impl<C: Cell> PagedMemory<C> {
+    pub fn new(code: &[C]) -> Self {
+        Self::with_limits(code, MemoryLimits::default())
+    }
+
+    /// Loads the program regardless of the limits, they only apply to later writes.
+    pub fn with_limits(code: &[C], limits: MemoryLimits) -> Self {
+        let mut pages: BTreeMap<usize, Vec<C>> = BTreeMap::new();
+        for (index, chunk) in code.chunks(PAGE_SIZE).enumerate() {
+            let mut page = chunk.to_vec();
+            page.resize(PAGE_SIZE, C::from_i64(0));
+            pages.insert(index, page);
+        }
+        Self{pages, len: code.len(), limits}
+    }
+
+    /// Number of allocated pages.
+    pub fn pages(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+impl<C: Cell> Memory for PagedMemory<C> {
+    type Cell = C;
+
+    fn read(&self, addr: usize) -> Result<C, LimitExceeded> {
+        if addr > self.limits.max_addr {
+            return Err(LimitExceeded { target: addr })
+        }
+        Ok(self.pages.get(&(addr / PAGE_SIZE)).map_or_else(|| C::from_i64(0), |page| page[addr % PAGE_SIZE].clone()))
+    }
+
+    fn write(&mut self, addr: usize, value: C) -> Result<(), LimitExceeded> {
+        if addr > self.limits.max_addr {
+            return Err(LimitExceeded { target: addr })
+        }
+        let index = addr / PAGE_SIZE;
+        if !self.pages.contains_key(&index) {
+            if value.is_zero() {
+                // the cell reads as zero anyway, there is no need to allocate a page for it
+                return Ok(())
+            }
+            if self.pages.len() >= self.limits.max_pages {
+                return Err(LimitExceeded { target: addr })
+            }
+            self.pages.insert(index, vec![C::from_i64(0); PAGE_SIZE]);
+        }
+        if let Some(page) = self.pages.get_mut(&index) {
+            page[addr % PAGE_SIZE] = value;
+        }
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn nonzero(&self) -> Vec<(usize, C)> {
+        self.pages.iter()
+            .flat_map(|(&index, page)| page.iter().enumerate().map(move |(offset, value)| (index * PAGE_SIZE + offset, value)))
+            .filter(|(_, value)| !value.is_zero())
+            .map(|(addr, value)| (addr, value.clone()))
+            .collect()
+    }
+}
+
+
+/// Copy-on-write memory over a program shared with other instances.
+///
+/// Only the cells written by this instance are stored, so making one is as cheap as
+/// cloning an `Arc` no matter how long the program is.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct PatchedMemory {
+    base: Arc<Vec<i64>>,
+    // cells written since the instance was made
+    overlay: BTreeMap<usize, i64>,
+    pub limits: MemoryLimits
+}
+
+impl PatchedMemory {
+    pub fn new(base: Arc<Vec<i64>>) -> Self {
+        Self{base, overlay: BTreeMap::new(), limits: MemoryLimits::default()}
+    }
+
+    /// Number of cells that differ from the shared program, or lie past it.
+    pub fn written(&self) -> usize {
+        self.overlay.len()
+    }
+}
+
+impl Memory for PatchedMemory {
+    type Cell = i64;
+
+    fn read(&self, addr: usize) -> Result<i64, LimitExceeded> {
+        if addr > self.limits.max_addr {
+            return Err(LimitExceeded { target: addr })
+        }
+        Ok(match self.overlay.get(&addr) {
+            Some(&value) => value,
+            None => self.base.get(addr).cloned().unwrap_or(0),
+        })
+    }
+
+    fn write(&mut self, addr: usize, value: i64) -> Result<(), LimitExceeded> {
+        if addr > self.limits.max_addr {
+            return Err(LimitExceeded { target: addr })
+        }
+        // the same cell budget as a `PagedMemory` full of pages
+        if self.overlay.len() >= self.limits.max_pages * PAGE_SIZE && !self.overlay.contains_key(&addr) {
+            return Err(LimitExceeded { target: addr })
+        }
+        self.overlay.insert(addr, value);
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.base.len()
+    }
+
+    fn nonzero(&self) -> Vec<(usize, i64)> {
+        let shared = self.base.iter().enumerate().filter(|(addr, _)| !self.overlay.contains_key(addr));
+        let mut cells: Vec<(usize, i64)> = shared.map(|(addr, &value)| (addr, value))
+            .chain(self.overlay.iter().map(|(&addr, &value)| (addr, value)))
+            .filter(|&(_, value)| value != 0)
+            .collect();
+        cells.sort_unstable();
+        cells
+    }
+}
+
+
+/// Flat memory growing up to the highest written address, like the original day 9 VM.
+///
+/// Unlike the other models the whole vector counts as the program, cells written past
+/// the loaded code included.
+impl<C: Cell> Memory for Vec<C> {
+    type Cell = C;
+
+    fn read(&self, addr: usize) -> Result<C, LimitExceeded> {
+        Ok(self.get(addr).cloned().unwrap_or_else(|| C::from_i64(0)))
+    }
+
+    fn write(&mut self, addr: usize, value: C) -> Result<(), LimitExceeded> {
+        if addr > MemoryLimits::default().flat_max_addr() {
+            return Err(LimitExceeded { target: addr })
+        }
+        if addr >= self.len() {
+            self.resize(addr + 1, C::from_i64(0));
+        }
+        self[addr] = value;
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn to_vec(&self) -> Vec<C> {
+        self.clone()
+    }
+}
@@ -0,0 +1,179 @@
+//! A cluster of Intcode machines sending packets to each other, as on day 23.
+//!
+//! Machine `n` of `N` gets its address `n` as the first input, then reads packets as `X`
+//! and `Y` values, or `-1` whenever its queue is empty. Every three output values form a
+//! packet `(dest, X, Y)`. Packets for addresses outside `0..N` go to the `Nat` hooks.
+//!
+//! The machines take turns: the input streams are blocking, so `run` gives control back
+//! after every consumed value and no machine can starve the others while it polls.
+
+use crate::{Intcode, RunState, VmResult, IO};
+
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Packet {
+    pub from: usize,
+    pub dest: i64,
+    pub x: i64,
+    pub y: i64
+}
+
+/// Why `Network::run` stopped.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Stopped {
+    // one of the `Nat` hooks asked for it
+    Nat,
+    // every machine has halted
+    Halted,
+}
+
+/// Hooks for the addresses outside the network.
+pub trait NatHooks {
+    /// A packet sent to an address that is not a machine. `false` stops the network.
+    fn receive(&mut self, packet: Packet) -> bool;
+
+    /// Every machine keeps waiting on an empty queue. The returned packet is delivered to
+    /// its destination, `None` stops the network.
+    fn idle(&mut self) -> Option<Packet>;
+}
+
+/// The NAT of day 23: remembers the last packet sent to its address and hands it to
+/// machine 0 whenever the network is idle. Stops once the same `Y` is handed over twice in a row.
+#[derive(Debug, Clone)]
+pub struct Nat {
+    pub address: i64,
+    pub last: Option<Packet>,
+    // `Y` of every packet handed to machine 0, oldest first
+    pub delivered: Vec<i64>
+}
+
+impl Nat {
+    pub fn new(address: i64) -> Self {
+        Self{address, last: None, delivered: vec![]}
+    }
+}
+
+impl NatHooks for Nat {
+    fn receive(&mut self, packet: Packet) -> bool {
+        // packets to unknown addresses are dropped, like on a real network
+        if packet.dest == self.address {
+            self.last = Some(packet);
+        }
+        true
+    }
+
+    fn idle(&mut self) -> Option<Packet> {
+        let last = self.last?;
+        if self.delivered.last() == Some(&last.y) {
+            return None
+        }
+        self.delivered.push(last.y);
+        Some(Packet{from: last.from, dest: 0, ..last})
+    }
+}
+
+
+// `-1` reads in a row after which a machine counts as idle
+const IDLE_POLLS: usize = 2;
+
+#[derive(Debug, Clone)]
+struct Node {
+    program: Intcode,
+    input: IO,
+    output: IO,
+    // `-1` reads since the last packet the machine has received or sent
+    polls: usize,
+    halted: bool
+}
+
+#[derive(Debug, Clone)]
+pub struct Network {
+    nodes: Vec<Node>
+}
+
+impl Network {
+    /// Boots `size` copies of `program` with addresses `0..size`.
+    pub fn new(program: &Intcode, size: usize) -> Self {
+        let nodes = (0..size).map(|address| {
+            let mut input = IO::new(true);
+            input.push_input(address as i64);
+            Node{program: program.clone(), input, output: IO::new(false), polls: 0, halted: false}
+        }).collect();
+        Self{nodes}
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn machine(&self, address: usize) -> &Intcode {
+        &self.nodes[address].program
+    }
+
+    /// Queues a packet for machine `dest`, e.g. one made up by the caller.
+    pub fn send(&mut self, dest: usize, x: i64, y: i64) {
+        let node = &mut self.nodes[dest];
+        node.input.extend_inputs(vec![x, y]);
+        node.polls = 0;
+    }
+
+    /// Every machine has halted or keeps reading `-1` from an empty queue.
+    pub fn is_idle(&self) -> bool {
+        self.nodes.iter().all(|node| node.halted || node.polls >= IDLE_POLLS && node.input.stream.is_empty())
+    }
+
+    /// Lets every machine consume one input value and routes the packets produced meanwhile.
+    ///
+    /// `Some` once the network has to stop.
+    pub fn round<N: NatHooks>(&mut self, nat: &mut N) -> VmResult<Option<Stopped>> {
+        let mut packets: Vec<Packet> = vec![];
+        for (address, node) in self.nodes.iter_mut().enumerate() {
+            if node.halted {
+                continue;
+            }
+            if node.input.stream.is_empty() {
+                node.input.push_input(-1);
+                node.polls += 1;
+            }
+            node.halted = node.program.run(&mut node.input, &mut node.output)? == RunState::Halted;
+
+            let sent = node.output.take_output_triples();
+            if !sent.is_empty() {
+                node.polls = 0;
+            }
+            packets.extend(sent.into_iter().map(|(dest, x, y)| Packet{from: address, dest, x, y}));
+        }
+
+        for packet in packets {
+            if (0..self.len() as i64).contains(&packet.dest) {
+                self.send(packet.dest as usize, packet.x, packet.y);
+            } else if !nat.receive(packet) {
+                return Ok(Some(Stopped::Nat))
+            }
+        }
+
+        if self.nodes.iter().all(|node| node.halted) {
+            return Ok(Some(Stopped::Halted))
+        }
+        if self.is_idle() {
+            match nat.idle() {
+                Some(packet) if (0..self.len() as i64).contains(&packet.dest) => self.send(packet.dest as usize, packet.x, packet.y),
+                _ => return Ok(Some(Stopped::Nat)),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Runs rounds until the NAT hooks stop the network or every machine halts.
+    pub fn run<N: NatHooks>(&mut self, nat: &mut N) -> VmResult<Stopped> {
+        loop {
+            if let Some(stopped) = self.round(nat)? {
+                return Ok(stopped)
+            }
+        }
+    }
+}
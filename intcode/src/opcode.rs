@@ -0,0 +1,111 @@
+//! Instruction and parameter mode encoding, shared by the VM and the tooling around it.
+
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum Opcode {
+    Add, // 1
+    Mult, // 2
+    Input, // 3
+    Output, // 4
+    JumpTrue, // 5
+    JumpFalse, // 6
+    Less, // 7
+    Equals, // 8
+    AdjustBase, // 9
+    Halt, // 99
+}
+
+pub const OPCODES: [Opcode; 10] = [
+    Opcode::Add, Opcode::Mult, Opcode::Input, Opcode::Output, Opcode::JumpTrue,
+    Opcode::JumpFalse, Opcode::Less, Opcode::Equals, Opcode::AdjustBase, Opcode::Halt,
+];
+
+impl Opcode {
+    /// Decodes the two lowest digits of an instruction.
+    pub fn from_code(code: i64) -> Option<Self> {
+        match code {
+            1 => Some(Opcode::Add),
+            2 => Some(Opcode::Mult),
+            3 => Some(Opcode::Input),
+            4 => Some(Opcode::Output),
+            5 => Some(Opcode::JumpTrue),
+            6 => Some(Opcode::JumpFalse),
+            7 => Some(Opcode::Less),
+            8 => Some(Opcode::Equals),
+            9 => Some(Opcode::AdjustBase),
+            99 => Some(Opcode::Halt),
+            _ => None,
+        }
+    }
+
+    pub fn code(self) -> i64 {
+        match self {
+            Opcode::Add => 1,
+            Opcode::Mult => 2,
+            Opcode::Input => 3,
+            Opcode::Output => 4,
+            Opcode::JumpTrue => 5,
+            Opcode::JumpFalse => 6,
+            Opcode::Less => 7,
+            Opcode::Equals => 8,
+            Opcode::AdjustBase => 9,
+            Opcode::Halt => 99,
+        }
+    }
+
+    pub fn params_num(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Mult | Opcode::Less | Opcode::Equals => 3,
+            Opcode::JumpTrue | Opcode::JumpFalse => 2,
+            Opcode::Input | Opcode::Output | Opcode::AdjustBase => 1,
+            Opcode::Halt => 0,
+        }
+    }
+
+    /// Whether the last parameter is the address the result is written to.
+    pub fn writes(self) -> bool {
+        matches!(self, Opcode::Add | Opcode::Mult | Opcode::Input | Opcode::Less | Opcode::Equals)
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "add",
+            Opcode::Mult => "mul",
+            Opcode::Input => "in",
+            Opcode::Output => "out",
+            Opcode::JumpTrue => "jt",
+            Opcode::JumpFalse => "jf",
+            Opcode::Less => "lt",
+            Opcode::Equals => "eq",
+            Opcode::AdjustBase => "arb",
+            Opcode::Halt => "hlt",
+        }
+    }
+}
+
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum Mode {
+    Position, // 0
+    Immediate, // 1
+    Relative, // 2
+}
+
+impl Mode {
+    pub fn from_digit(digit: i64) -> Option<Self> {
+        match digit {
+            0 => Some(Mode::Position),
+            1 => Some(Mode::Immediate),
+            2 => Some(Mode::Relative),
+            _ => None,
+        }
+    }
+
+    pub fn digit(self) -> i64 {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2,
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Named memory patches and cheap patched copies of a program.
+//!
+//! Puzzles often ask to change a few cells before running, e.g. "set address 0 to 2 to
+//! enable free play". A `Patch` is such a list of address -> value pairs, a `PatchSet`
+//! names them and can be loaded from TOML or JSON:
+//!
+//! ```toml
+//! [free-play]
+//! 0 = 2
+//! ```
+//!
+//! `Program` parses the text once and hands out instances sharing its memory, each one
+//! copying only the cells it writes.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Intcode, Memory, PatchedMemory, VmError, VmResult};
+
+
+/// Values to put into memory cells, by address.
+#[derive(Debug, Eq, PartialEq, Clone, Default, Serialize, Deserialize)]
+// TOML keys are always strings, the addresses are parsed and checked on load
+#[serde(try_from = "BTreeMap<String, i64>", into = "BTreeMap<String, i64>")]
+pub struct Patch {
+    cells: BTreeMap<usize, i64>
+}
+
+impl Patch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The patch with one more cell set.
+    pub fn with(mut self, addr: usize, value: i64) -> Self {
+        self.cells.insert(addr, value);
+        self
+    }
+
+    /// The address -> value pairs in address order.
+    pub fn cells(&self) -> Vec<(usize, i64)> {
+        self.cells.iter().map(|(&addr, &value)| (addr, value)).collect()
+    }
+
+    /// Writes the cells into `memory`, before a program is made from it.
+    ///
+    /// The cells are only decoded once all of them are written, so a patch may replace the
+    /// instruction at address 0 by one that reads the following cells as parameters.
+    pub fn apply<M: Memory<Cell = i64>>(&self, memory: &mut M) -> VmResult<()> {
+        for (addr, value) in self.cells() {
+            memory.write(addr, value).map_err(|limit| VmError::BadPatch {
+                reason: format!("address {} is beyond the memory limits", limit.target)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Fails on keys that are not addresses, and on two keys for the same one, e.g. `1` and `01`.
+impl TryFrom<BTreeMap<String, i64>> for Patch {
+    // becomes the reason of a `VmError::BadPatch` when loading a `PatchSet`
+    type Error = String;
+
+    fn try_from(keys: BTreeMap<String, i64>) -> Result<Self, String> {
+        let mut cells: BTreeMap<usize, i64> = BTreeMap::new();
+        for (key, value) in keys {
+            let addr = key.trim().parse::<usize>().map_err(|_| format!("{:?} is not an address", key))?;
+            if cells.insert(addr, value).is_some() {
+                return Err(format!("address {} is set more than once", addr))
+            }
+        }
+        Ok(Self{cells})
+    }
+}
+
+impl From<Patch> for BTreeMap<String, i64> {
+    fn from(patch: Patch) -> Self {
+        patch.cells.into_iter().map(|(addr, value)| (addr.to_string(), value)).collect()
+    }
+}
+
+impl FromIterator<(usize, i64)> for Patch {
+    fn from_iter<I: IntoIterator<Item = (usize, i64)>>(cells: I) -> Self {
+        cells.into_iter().fold(Self::new(), |patch, (addr, value)| patch.with(addr, value))
+    }
+}
+
+
+#[derive(Debug, Eq, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PatchSet {
+    patches: BTreeMap<String, Patch>
+}
+
+impl PatchSet {
+    pub fn from_toml(text: &str) -> VmResult<Self> {
+        toml::from_str(text).map_err(|error| VmError::BadPatch { reason: error.to_string() })
+    }
+
+    pub fn from_json(text: &str) -> VmResult<Self> {
+        serde_json::from_str(text).map_err(|error| VmError::BadPatch { reason: error.to_string() })
+    }
+
+    pub fn insert(&mut self, name: &str, patch: Patch) {
+        self.patches.insert(name.to_string(), patch);
+    }
+
+    pub fn get(&self, name: &str) -> VmResult<&Patch> {
+        self.patches.get(name).ok_or_else(|| VmError::BadPatch { reason: format!("no patch named {:?}", name) })
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.patches.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+
+/// A parsed program, instances made from it share its memory.
+#[derive(Debug, Clone)]
+pub struct Program {
+    code: Arc<Vec<i64>>
+}
+
+impl Program {
+    pub fn new(data: &str) -> VmResult<Self> {
+        Ok(Self::from_code(crate::parse(data)?))
+    }
+
+    pub fn from_code(code: Vec<i64>) -> Self {
+        Self{code: Arc::new(code)}
+    }
+
+    pub fn code(&self) -> &[i64] {
+        &self.code
+    }
+
+    /// A fresh instance of the program, ready to run.
+    pub fn instance(&self) -> VmResult<Intcode<PatchedMemory>> {
+        self.patched(&[])
+    }
+
+    /// An instance with `patches` applied in order, later ones win on shared cells.
+    pub fn patched(&self, patches: &[&Patch]) -> VmResult<Intcode<PatchedMemory>> {
+        let mut memory = PatchedMemory::new(Arc::clone(&self.code));
+        for patch in patches {
+            patch.apply(&mut memory)?;
+        }
+        Intcode::with_memory(memory)
+    }
+
+    /// An instance with the patches of `set` named in `names` applied in order.
+    pub fn variant(&self, set: &PatchSet, names: &[&str]) -> VmResult<Intcode<PatchedMemory>> {
+        let patches = names.iter().map(|name| set.get(name)).collect::<VmResult<Vec<&Patch>>>()?;
+        self.patched(&patches)
+    }
+}
@@ -0,0 +1,143 @@
+//! Instruction profiler, a `Tracer` counting where the cycles of a run go.
+//!
+//! Every executed instruction is counted per address and per opcode, every memory cell
+//! read through a position or relative parameter and every written cell per address.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::disasm::{decode, decode_with, reachable, Instruction};
+use crate::{Intcode, Memory, Mode, Opcode, Tracer, OPCODES};
+
+
+// cells per row of the heat maps
+const REGION: usize = 64;
+const BAR_WIDTH: u64 = 40;
+
+#[derive(Debug, Default, Clone)]
+pub struct Profile {
+    pub cycles: u64,
+    pub executions: BTreeMap<usize, u64>,
+    pub opcodes: HashMap<Opcode, u64>,
+    pub reads: BTreeMap<usize, u64>,
+    pub writes: BTreeMap<usize, u64>,
+    // counted only once the instruction has run, an `Input` waiting for a value runs again later
+    pending: Option<(usize, Opcode, Vec<usize>, Option<usize>)>
+}
+
+impl Tracer for Profile {
+    fn before_step<M: Memory<Cell = i64>>(&mut self, vm: &Intcode<M>) {
+        let instruction = decode_with(|addr| Some(vm.peek(addr)), vm.iptr);
+        if let Some(Instruction::Code { opcode, params, .. }) = instruction {
+            let reads_num = if opcode.writes() { params.len() - 1 } else { params.len() };
+            let reads: Vec<usize> = params[..reads_num].iter()
+                .filter_map(|param| match param.mode {
+                    Mode::Position => Some(param.value),
+                    Mode::Relative => Some(vm.base.wrapping_add(param.value)),
+                    Mode::Immediate => None,
+                })
+                .filter(|&addr| addr >= 0)
+                .map(|addr| addr as usize)
+                .collect();
+            self.pending = Some((vm.iptr, opcode, reads, vm.op.write_addr()));
+        }
+    }
+
+    fn after_step<M: Memory<Cell = i64>>(&mut self, _vm: &Intcode<M>) {
+        if let Some((iptr, opcode, reads, write)) = self.pending.take() {
+            self.cycles += 1;
+            *self.executions.entry(iptr).or_default() += 1;
+            *self.opcodes.entry(opcode).or_default() += 1;
+            for addr in reads {
+                *self.reads.entry(addr).or_default() += 1;
+            }
+            if let Some(addr) = write {
+                *self.writes.entry(addr).or_default() += 1;
+            }
+        }
+    }
+}
+
+fn hottest(counts: &BTreeMap<usize, u64>, top: usize) -> Vec<(usize, u64)> {
+    let mut sorted: Vec<(usize, u64)> = counts.iter().map(|(&addr, &count)| (addr, count)).collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    sorted.truncate(top);
+    sorted
+}
+
+/// One row per `REGION` cells that were touched at all, with a bar relative to the busiest row.
+fn heat_map(counts: &BTreeMap<usize, u64>) -> String {
+    let mut regions: BTreeMap<usize, u64> = BTreeMap::new();
+    for (&addr, &count) in counts {
+        *regions.entry(addr / REGION).or_default() += count;
+    }
+    let max = regions.values().cloned().max().unwrap_or(1).max(1);
+    let rows: Vec<String> = regions.iter()
+        .map(|(&region, &count)| format!(
+            "{:04}..{:04} {:<width$} {}",
+            region * REGION, (region + 1) * REGION - 1, "#".repeat((count * BAR_WIDTH).div_ceil(max) as usize), count,
+            width = BAR_WIDTH as usize
+        ))
+        .collect();
+    rows.join("\n")
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Totals, opcodes by count and the `top` busiest addresses, followed by the heat maps.
+    pub fn report(&self, top: usize) -> String {
+        let percent = |count: u64| 100.0 * count as f64 / self.cycles.max(1) as f64;
+        let mut lines: Vec<String> = vec![format!("total cycles: {}", self.cycles)];
+
+        let mut opcodes: Vec<(Opcode, u64)> = OPCODES.iter()
+            .filter_map(|opcode| self.opcodes.get(opcode).map(|&count| (*opcode, count)))
+            .collect();
+        opcodes.sort_by_key(|&(_, count)| Reverse(count));
+        lines.push("\nopcodes:".to_string());
+        for (opcode, count) in opcodes {
+            lines.push(format!("  {:<4} {:>12} {:>6.2}%", opcode.mnemonic(), count, percent(count)));
+        }
+
+        lines.push("\nhottest instructions:".to_string());
+        for (addr, count) in hottest(&self.executions, top) {
+            lines.push(format!("  {:04} {:>12} {:>6.2}%", addr, count, percent(count)));
+        }
+        lines.push("\nmost read cells:".to_string());
+        for (addr, count) in hottest(&self.reads, top) {
+            lines.push(format!("  {:04} {:>12}", addr, count));
+        }
+        lines.push("\nmost written cells:".to_string());
+        for (addr, count) in hottest(&self.writes, top) {
+            lines.push(format!("  {:04} {:>12}", addr, count));
+        }
+
+        lines.push(format!("\nread heat map:\n{}", heat_map(&self.reads)));
+        lines.push(format!("\nwrite heat map:\n{}", heat_map(&self.writes)));
+        lines.join("\n")
+    }
+
+    /// The disassembly listing of `code` with execution counts in front of every line.
+    ///
+    /// Executed addresses are listed as instructions even where the static walk of
+    /// `disasm::disassemble` does not get, e.g. behind computed jumps.
+    pub fn annotate(&self, code: &[i64]) -> String {
+        let starts = reachable(code);
+        let mut lines: Vec<String> = vec![];
+        let mut addr: usize = 0;
+        while addr < code.len() {
+            let instruction = match decode(code, addr) {
+                Some(instruction) if starts.contains(&addr) || self.executions.contains_key(&addr) => instruction,
+                _ => Instruction::Data { addr, value: code[addr] },
+            };
+            match self.executions.get(&addr) {
+                Some(count) => lines.push(format!("{:>12} | {}", count, instruction)),
+                None => lines.push(format!("{:>12} | {}", "", instruction)),
+            }
+            addr += instruction.size();
+        }
+        lines.join("\n")
+    }
+}
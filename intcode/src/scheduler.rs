@@ -0,0 +1,191 @@
+//! Cooperative scheduling of many machines on one thread.
+//!
+//! Machines are wired up with edges from the output of one machine to the input of another,
+//! so chains, rings and meshes are all just lists of edges. A machine runs until it blocks on
+//! an empty input or uses up its time slice, then the next one is picked by the policy.
+
+use std::collections::VecDeque;
+
+use crate::{Intcode, Limits, RunState, VmError, VmResult, IO};
+
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Policy {
+    // every machine that can run gets a slice in turn
+    RoundRobin,
+    // the machine with the highest priority that can run, ties go to the lower id
+    Priority,
+}
+
+/// Why `Scheduler::run` stopped.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum Stopped {
+    // every machine has halted
+    Halted,
+    // the machines that have not halted all wait for input, and none is pending
+    Deadlock { blocked: Vec<usize> },
+    // `max_slices` slices have been given out
+    OutOfSlices,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+enum Status {
+    Ready,
+    Blocked,
+    Halted,
+}
+
+#[derive(Debug, Clone)]
+struct Task {
+    program: Intcode,
+    input: IO,
+    output: IO,
+    priority: i64,
+    status: Status
+}
+
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    tasks: Vec<Task>,
+    // (from, to): outputs of `from` are appended to the input of `to`
+    edges: Vec<(usize, usize)>,
+    pub policy: Policy,
+    // instructions a machine runs before another one gets its turn, never zero
+    slice: u64,
+    // where round-robin continues
+    next: usize
+}
+
+impl Scheduler {
+    /// `slice` has to be at least one instruction, machines would never make progress otherwise.
+    pub fn new(policy: Policy, slice: u64) -> VmResult<Self> {
+        if slice == 0 {
+            return Err(VmError::BadSchedule { reason: "the time slice is empty".to_string() })
+        }
+        Ok(Self{tasks: vec![], edges: vec![], policy, slice, next: 0})
+    }
+
+    pub fn slice(&self) -> u64 {
+        self.slice
+    }
+
+    /// Adds a machine, its id is the number of machines added before.
+    pub fn add(&mut self, program: Intcode, priority: i64) -> usize {
+        self.tasks.push(Task{program, input: IO::new(false), output: IO::new(false), priority, status: Status::Ready});
+        self.tasks.len() - 1
+    }
+
+    pub fn connect(&mut self, from: usize, to: usize) {
+        self.edges.push((from, to));
+    }
+
+    /// Each machine feeds the next one.
+    pub fn chain(&mut self, ids: &[usize]) {
+        for pair in ids.windows(2) {
+            self.connect(pair[0], pair[1]);
+        }
+    }
+
+    /// A chain where the last machine feeds the first one.
+    pub fn ring(&mut self, ids: &[usize]) {
+        self.chain(ids);
+        if let (Some(&first), Some(&last)) = (ids.first(), ids.last()) {
+            self.connect(last, first);
+        }
+    }
+
+    /// Every machine feeds every other one.
+    pub fn mesh(&mut self, ids: &[usize]) {
+        for &from in ids {
+            for &to in ids.iter().filter(|&&to| to != from) {
+                self.connect(from, to);
+            }
+        }
+    }
+
+    pub fn machine(&self, id: usize) -> &Intcode {
+        &self.tasks[id].program
+    }
+
+    pub fn push_input(&mut self, id: usize, value: i64) {
+        self.tasks[id].input.push_input(value);
+    }
+
+    /// Values waiting to be read by machine `id`, e.g. the last signal in a ring that has halted.
+    pub fn pending_input(&self, id: usize) -> &VecDeque<i64> {
+        &self.tasks[id].input.stream
+    }
+
+    /// Takes the outputs of a machine that has no outgoing edges.
+    pub fn take_output(&mut self, id: usize) -> Vec<i64> {
+        self.tasks[id].output.drain_outputs()
+    }
+
+    fn runnable(&self, id: usize) -> bool {
+        let task = &self.tasks[id];
+        match task.status {
+            Status::Ready => true,
+            Status::Blocked => !task.input.stream.is_empty(),
+            Status::Halted => false,
+        }
+    }
+
+    fn pick(&mut self) -> Option<usize> {
+        let count = self.tasks.len();
+        match self.policy {
+            Policy::RoundRobin => {
+                let id = (0..count).map(|offset| (self.next + offset) % count).find(|&id| self.runnable(id))?;
+                self.next = (id + 1) % count;
+                Some(id)
+            },
+            Policy::Priority => (0..count).filter(|&id| self.runnable(id))
+                .max_by_key(|&id| (self.tasks[id].priority, std::cmp::Reverse(id))),
+        }
+    }
+
+    /// Runs machine `id` for one slice and delivers what it has written.
+    fn run_slice(&mut self, id: usize) -> VmResult<()> {
+        let limits = Limits{max_steps: Some(self.slice), ..Limits::default()};
+        let task = &mut self.tasks[id];
+        task.status = match task.program.run_with_limits(&mut task.input, &mut task.output, &limits)? {
+            RunState::Halted => Status::Halted,
+            RunState::NeedsInput => Status::Blocked,
+            // out of the time slice, the streams never block and no other limit is set
+            _ => Status::Ready,
+        };
+
+        let targets: Vec<usize> = self.edges.iter().filter(|&&(from, _)| from == id).map(|&(_, to)| to).collect();
+        if targets.is_empty() {
+            return Ok(())
+        }
+        let values = self.tasks[id].output.drain_outputs();
+        for to in targets {
+            self.tasks[to].input.extend_inputs(values.iter().cloned());
+        }
+        Ok(())
+    }
+
+    /// Runs until every machine halts or all of them are stuck, see `run_for`.
+    pub fn run(&mut self) -> VmResult<Stopped> {
+        self.run_for(u64::MAX)
+    }
+
+    /// Hands out at most `max_slices` time slices.
+    ///
+    /// After `Deadlock` the caller may push input to the blocked machines and run again.
+    pub fn run_for(&mut self, max_slices: u64) -> VmResult<Stopped> {
+        for _ in 0..max_slices {
+            match self.pick() {
+                Some(id) => self.run_slice(id)?,
+                None => {
+                    let blocked: Vec<usize> = (0..self.tasks.len()).filter(|&id| self.tasks[id].status == Status::Blocked).collect();
+                    if blocked.is_empty() {
+                        return Ok(Stopped::Halted)
+                    }
+                    return Ok(Stopped::Deadlock { blocked })
+                },
+            }
+        }
+        Ok(Stopped::OutOfSlices)
+    }
+}
@@ -0,0 +1,68 @@
+//! Saving a whole machine, its memory, registers and I/O queues, to disk and back.
+//!
+//! Snapshots are JSON documents carrying a format version, loading refuses any other one.
+
+use std::io::{self, Read, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Intcode, Op, VmError, VmResult, IO};
+
+
+pub const SNAPSHOT_VERSION: u32 = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub vm: Intcode,
+    pub input: IO,
+    pub output: IO
+}
+
+impl Snapshot {
+    pub fn save<W: Write>(&self, writer: W) -> io::Result<()> {
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    /// Reads a snapshot, memory that could not have been saved is refused as well.
+    pub fn load<R: Read>(reader: R) -> VmResult<Self> {
+        serde_json::from_reader(reader).map_err(|error| VmError::BadSnapshot { reason: error.to_string() })
+    }
+}
+
+
+impl Intcode {
+    pub fn snapshot(&self, input: &IO, output: &IO) -> Snapshot {
+        Snapshot{
+            version: SNAPSHOT_VERSION,
+            vm: self.clone(),
+            input: input.clone(),
+            output: output.clone()
+        }
+    }
+
+    /// Restores the machine with its input and output queues.
+    ///
+    /// The pending op is decoded again from the saved memory and has to match the saved one.
+    pub fn from_snapshot(snapshot: Snapshot) -> VmResult<(Self, IO, IO)> {
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(VmError::BadSnapshot {
+                reason: format!("version {} is not supported, expected {}", snapshot.version, SNAPSHOT_VERSION)
+            })
+        }
+
+        let vm = snapshot.vm;
+        let op = if vm.finished() {
+            Op::Halt
+        } else {
+            Self::parse_op(&vm.memory, vm.iptr, vm.base)?
+        };
+        if op != vm.op {
+            return Err(VmError::BadSnapshot {
+                reason: format!("pending op {:?} does not match {:?} found in memory at {}", vm.op, op, vm.iptr)
+            })
+        }
+        Ok((vm, snapshot.input, snapshot.output))
+    }
+}
@@ -0,0 +1,481 @@
+//! Symbolic execution, solving for the inputs that make a program produce a given value.
+//!
+//! Chosen memory cells and input values are variables with a range of allowed values.
+//! Running the program builds an expression for every value computed from them, constants
+//! are folded on the way, so the concrete part of the program runs as usual. Reading
+//! through an address that depends on a variable gives a `Load` from the memory as it was at
+//! that moment, it is only resolved when the expression is evaluated.
+//!
+//! Only a single path is followed: a jump condition or target, an instruction word, a
+//! relative base adjustment or a write address that depends on a variable stops the run
+//! with `VmError::TooComplex`.
+//!
+//! A target value is solved for directly when the expression is linear in the variables,
+//! as day 2 is, otherwise the variables are enumerated within their ranges.
+//!
+//! Expressions share their operands, e.g. doubling a cell in a loop gives a chain of `Add`s
+//! of one operand with itself. Walks over them visit every node once, by its address, so
+//! their cost follows the number of nodes and not the size of the expression written out.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::{Mode, Opcode, VmError, VmResult};
+
+
+pub type Value = Rc<Expr>;
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Const(i64),
+    // index into the variables of `Symbolic`
+    Var(usize),
+    Add(Value, Value),
+    Mul(Value, Value),
+    Less(Value, Value),
+    Equals(Value, Value),
+    // the cell at a symbolic address, in the memory as it was when it has been read
+    Load(Value, Rc<Vec<Value>>),
+}
+
+// sum of `terms` (variable index -> coefficient) plus a constant
+type Linear = (i64, BTreeMap<usize, i64>);
+
+// more candidates than this are not enumerated
+const MAX_CANDIDATES: u128 = 10_000_000;
+// nodes written out by `describe`, the rest of the expression is left out
+const MAX_DESCRIBED: usize = 64;
+
+fn constant(value: i64) -> Value {
+    Rc::new(Expr::Const(value))
+}
+
+fn too_complex(reason: String) -> VmError {
+    VmError::TooComplex { reason }
+}
+
+/// The smallest `x` in `range` with `coefficient * x == value` in wrapping arithmetic.
+fn divide(value: i64, coefficient: i64, range: &Range<i64>) -> Option<i64> {
+    // the product keeps the trailing zero bits of `coefficient`, the odd rest is invertible
+    let shift = coefficient.trailing_zeros();
+    if coefficient == 0 || value.trailing_zeros() < shift {
+        return None
+    }
+    let odd = coefficient as u64 >> shift;
+    // Newton's iteration doubles the correct low bits, starting with 3 of them
+    let mut inverse = odd;
+    for _ in 0..5 {
+        inverse = inverse.wrapping_mul(2u64.wrapping_sub(odd.wrapping_mul(inverse)));
+    }
+    // the solutions repeat every 2^(64 - shift)
+    let period: i128 = 1 << (64 - shift);
+    let solution = ((value as u64 >> shift).wrapping_mul(inverse) as i128).rem_euclid(period);
+    let first = range.start as i128 + (solution - range.start as i128).rem_euclid(period);
+    if first < range.end as i128 { Some(first as i64) } else { None }
+}
+
+impl Expr {
+    pub fn as_const(&self) -> Option<i64> {
+        match *self {
+            Expr::Const(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn add(left: Value, right: Value) -> Value {
+        match (left.as_const(), right.as_const()) {
+            (Some(a), Some(b)) => constant(a.wrapping_add(b)),
+            (Some(0), _) => right,
+            (_, Some(0)) => left,
+            _ => Rc::new(Expr::Add(left, right)),
+        }
+    }
+
+    fn mul(left: Value, right: Value) -> Value {
+        match (left.as_const(), right.as_const()) {
+            (Some(a), Some(b)) => constant(a.wrapping_mul(b)),
+            (Some(0), _) | (_, Some(0)) => constant(0),
+            (Some(1), _) => right,
+            (_, Some(1)) => left,
+            _ => Rc::new(Expr::Mul(left, right)),
+        }
+    }
+
+    fn less(left: Value, right: Value) -> Value {
+        match (left.as_const(), right.as_const()) {
+            (Some(a), Some(b)) => constant((a < b) as i64),
+            _ => Rc::new(Expr::Less(left, right)),
+        }
+    }
+
+    fn equals(left: Value, right: Value) -> Value {
+        match (left.as_const(), right.as_const()) {
+            (Some(a), Some(b)) => constant((a == b) as i64),
+            _ => Rc::new(Expr::Equals(left, right)),
+        }
+    }
+
+    /// Value for the given variables, `None` if it reads from a negative address.
+    pub fn eval(&self, vars: &[i64]) -> Option<i64> {
+        self.eval_with(vars, &mut HashMap::new())
+    }
+
+    // `known` holds the value of every node evaluated so far
+    fn eval_with(&self, vars: &[i64], known: &mut HashMap<*const Expr, Option<i64>>) -> Option<i64> {
+        if let Some(&value) = known.get(&(self as *const Expr)) {
+            return value
+        }
+        let value = self.eval_node(vars, known);
+        known.insert(self, value);
+        value
+    }
+
+    fn eval_node(&self, vars: &[i64], known: &mut HashMap<*const Expr, Option<i64>>) -> Option<i64> {
+        Some(match self {
+            Expr::Const(value) => *value,
+            Expr::Var(index) => vars[*index],
+            Expr::Add(left, right) => left.eval_with(vars, known)?.wrapping_add(right.eval_with(vars, known)?),
+            Expr::Mul(left, right) => left.eval_with(vars, known)?.wrapping_mul(right.eval_with(vars, known)?),
+            Expr::Less(left, right) => (left.eval_with(vars, known)? < right.eval_with(vars, known)?) as i64,
+            Expr::Equals(left, right) => (left.eval_with(vars, known)? == right.eval_with(vars, known)?) as i64,
+            Expr::Load(addr, memory) => {
+                let addr = addr.eval_with(vars, known)?;
+                if addr < 0 {
+                    return None
+                }
+                match memory.get(addr as usize) {
+                    Some(cell) => cell.eval_with(vars, known)?,
+                    None => 0,
+                }
+            },
+        })
+    }
+
+    /// Indices of the variables the value depends on.
+    pub fn variables(&self) -> BTreeSet<usize> {
+        let mut found: BTreeSet<usize> = BTreeSet::new();
+        self.collect_variables(&mut found, &mut HashSet::new(), &mut HashSet::new());
+        found
+    }
+
+    // `seen` and `memories` hold the nodes and the `Load` memories visited so far
+    fn collect_variables(&self, found: &mut BTreeSet<usize>, seen: &mut HashSet<*const Expr>, memories: &mut HashSet<*const Vec<Value>>) {
+        if !seen.insert(self) {
+            return
+        }
+        match self {
+            Expr::Const(_) => {},
+            Expr::Var(index) => { found.insert(*index); },
+            Expr::Add(left, right) | Expr::Mul(left, right) | Expr::Less(left, right) | Expr::Equals(left, right) => {
+                left.collect_variables(found, seen, memories);
+                right.collect_variables(found, seen, memories);
+            },
+            // only the cells that can actually be addressed matter, but all of them may be
+            Expr::Load(addr, memory) => {
+                addr.collect_variables(found, seen, memories);
+                if memories.insert(Rc::as_ptr(memory)) {
+                    for cell in memory.iter() {
+                        cell.collect_variables(found, seen, memories);
+                    }
+                }
+            },
+        }
+    }
+
+    /// The value as a linear combination of the variables, `None` if it is not one.
+    ///
+    /// The arithmetic wraps around as in `Intcode`, so the combination is exact modulo 2^64.
+    fn linear(&self) -> Option<Linear> {
+        self.linear_with(&mut HashMap::new())
+    }
+
+    // `known` holds the combination of every node looked at so far
+    fn linear_with(&self, known: &mut HashMap<*const Expr, Option<Linear>>) -> Option<Linear> {
+        if let Some(linear) = known.get(&(self as *const Expr)) {
+            return linear.clone()
+        }
+        let linear = self.linear_node(known);
+        known.insert(self, linear.clone());
+        linear
+    }
+
+    fn linear_node(&self, known: &mut HashMap<*const Expr, Option<Linear>>) -> Option<Linear> {
+        match self {
+            Expr::Const(value) => Some((*value, BTreeMap::new())),
+            Expr::Var(index) => Some((0, vec![(*index, 1)].into_iter().collect())),
+            Expr::Add(left, right) => {
+                let (mut sum, mut terms) = left.linear_with(known)?;
+                let (other, other_terms) = right.linear_with(known)?;
+                sum = sum.wrapping_add(other);
+                for (index, coefficient) in other_terms {
+                    let entry = terms.entry(index).or_insert(0);
+                    *entry = entry.wrapping_add(coefficient);
+                }
+                terms.retain(|_, coefficient| *coefficient != 0);
+                Some((sum, terms))
+            },
+            Expr::Mul(left, right) => {
+                let (left, right) = (left.linear_with(known)?, right.linear_with(known)?);
+                let ((factor, _), (value, terms)) = match (left.1.is_empty(), right.1.is_empty()) {
+                    (true, _) => (left, right),
+                    (_, true) => (right, left),
+                    _ => return None,
+                };
+                let terms = terms.into_iter()
+                    .map(|(index, coefficient)| (index, coefficient.wrapping_mul(factor)))
+                    .filter(|&(_, coefficient)| coefficient != 0)
+                    .collect();
+                Some((value.wrapping_mul(factor), terms))
+            },
+            _ => None,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub name: String,
+    pub range: Range<i64>
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbolic {
+    // shared with the `Load`s taken from it, copied on the next write
+    memory: Rc<Vec<Value>>,
+    pub iptr: usize,
+    pub base: i64,
+    pub variables: Vec<Variable>,
+    inputs: VecDeque<Value>,
+    pub outputs: Vec<Value>
+}
+
+impl Symbolic {
+    pub fn new(code: &[i64]) -> Self {
+        Self{
+            memory: Rc::new(code.iter().map(|&value| constant(value)).collect()),
+            iptr: 0,
+            base: 0,
+            variables: vec![],
+            inputs: VecDeque::new(),
+            outputs: vec![]
+        }
+    }
+
+    /// New variable taking values from `range`, it has to be non-empty.
+    pub fn variable(&mut self, name: &str, range: Range<i64>) -> Value {
+        self.variables.push(Variable{name: name.to_string(), range});
+        Rc::new(Expr::Var(self.variables.len() - 1))
+    }
+
+    pub fn constant(value: i64) -> Value {
+        constant(value)
+    }
+
+    pub fn peek(&self, addr: usize) -> Value {
+        self.memory.get(addr).cloned().unwrap_or_else(|| constant(0))
+    }
+
+    pub fn poke(&mut self, addr: usize, value: Value) {
+        let memory = Rc::make_mut(&mut self.memory);
+        if addr >= memory.len() {
+            memory.resize(addr + 1, constant(0));
+        }
+        memory[addr] = value;
+    }
+
+    pub fn push_input(&mut self, value: Value) {
+        self.inputs.push_back(value);
+    }
+
+    /// Expression with the variables written by name, cut short after `MAX_DESCRIBED` nodes.
+    pub fn describe(&self, expr: &Expr) -> String {
+        let mut budget = MAX_DESCRIBED;
+        self.describe_within(expr, &mut budget)
+    }
+
+    fn describe_within(&self, expr: &Expr, budget: &mut usize) -> String {
+        if *budget == 0 {
+            return "...".to_string()
+        }
+        *budget -= 1;
+        match expr {
+            Expr::Const(value) => value.to_string(),
+            Expr::Var(index) => self.variables[*index].name.clone(),
+            Expr::Add(left, right) => format!("({} + {})", self.describe_within(left, budget), self.describe_within(right, budget)),
+            Expr::Mul(left, right) => format!("{} * {}", self.describe_within(left, budget), self.describe_within(right, budget)),
+            Expr::Less(left, right) => format!("({} < {})", self.describe_within(left, budget), self.describe_within(right, budget)),
+            Expr::Equals(left, right) => format!("({} == {})", self.describe_within(left, budget), self.describe_within(right, budget)),
+            Expr::Load(addr, _) => format!("mem[{}]", self.describe_within(addr, budget)),
+        }
+    }
+
+    fn concrete(&self, value: &Value, what: &str) -> VmResult<i64> {
+        value.as_const().ok_or_else(|| too_complex(format!("{} at {} depends on {}", what, self.iptr, self.describe(value))))
+    }
+
+    fn address(&self, target: i64) -> VmResult<usize> {
+        if target < 0 {
+            return Err(VmError::NegativeAddress { addr: self.iptr, target })
+        }
+        Ok(target as usize)
+    }
+
+    /// Value of the parameter `index` (0-based) of the current instruction.
+    fn param(&self, modes: &[Mode; 3], index: usize) -> VmResult<Value> {
+        let word = self.peek(self.iptr + index + 1);
+        let target = match modes[index] {
+            Mode::Immediate => return Ok(word),
+            Mode::Position => word,
+            Mode::Relative => Expr::add(constant(self.base), word),
+        };
+        match target.as_const() {
+            Some(addr) => Ok(self.peek(self.address(addr)?)),
+            None => Ok(Rc::new(Expr::Load(target, Rc::clone(&self.memory)))),
+        }
+    }
+
+    /// Address the parameter `index` of the current instruction points to.
+    fn target(&self, modes: &[Mode; 3], index: usize) -> VmResult<usize> {
+        let word = self.concrete(&self.peek(self.iptr + index + 1), "write address")?;
+        match modes[index] {
+            Mode::Relative => self.address(self.base.wrapping_add(word)),
+            _ => self.address(word),
+        }
+    }
+
+    fn decode(&self) -> VmResult<(Opcode, [Mode; 3])> {
+        let word = self.concrete(&self.peek(self.iptr), "instruction")?;
+        let opcode = Opcode::from_code(word % 100).ok_or(VmError::BadOpcode { addr: self.iptr, opcode: word % 100 })?;
+        let mut modes = [Mode::Position; 3];
+        let mut acc = word / 100;
+        for mode in modes.iter_mut().take(opcode.params_num()) {
+            *mode = Mode::from_digit(acc % 10).ok_or(VmError::BadMode { addr: self.iptr, mode: acc % 10 })?;
+            acc /= 10;
+        }
+        Ok((opcode, modes))
+    }
+
+    /// Runs the program along its only path until it halts, at most `max_steps` instructions.
+    pub fn run(&mut self, max_steps: u64) -> VmResult<()> {
+        let mut steps: u64 = 0;
+        while self.iptr < self.memory.len() {
+            if steps == max_steps {
+                return Err(too_complex(format!("no halt within {} steps", max_steps)))
+            }
+            steps += 1;
+
+            let (opcode, modes) = self.decode()?;
+            match opcode {
+                Opcode::Add | Opcode::Mult | Opcode::Less | Opcode::Equals => {
+                    let (value1, value2) = (self.param(&modes, 0)?, self.param(&modes, 1)?);
+                    let addr = self.target(&modes, 2)?;
+                    let result = match opcode {
+                        Opcode::Add => Expr::add(value1, value2),
+                        Opcode::Mult => Expr::mul(value1, value2),
+                        Opcode::Less => Expr::less(value1, value2),
+                        _ => Expr::equals(value1, value2),
+                    };
+                    self.poke(addr, result);
+                    self.iptr += 4;
+                },
+                Opcode::Input => {
+                    let addr = self.target(&modes, 0)?;
+                    let value = self.inputs.pop_front().ok_or(VmError::MissingInput { addr: self.iptr })?;
+                    self.poke(addr, value);
+                    self.iptr += 2;
+                },
+                Opcode::Output => {
+                    let value = self.param(&modes, 0)?;
+                    self.outputs.push(value);
+                    self.iptr += 2;
+                },
+                Opcode::JumpTrue | Opcode::JumpFalse => {
+                    let value = self.concrete(&self.param(&modes, 0)?, "jump condition")?;
+                    let target = self.concrete(&self.param(&modes, 1)?, "jump target")?;
+                    if (value != 0) == (opcode == Opcode::JumpTrue) {
+                        if target < 0 || target as usize >= self.memory.len() {
+                            return Err(VmError::JumpOutOfBounds { addr: self.iptr, target })
+                        }
+                        self.iptr = target as usize;
+                    } else {
+                        self.iptr += 3;
+                    }
+                },
+                Opcode::AdjustBase => {
+                    let value = self.concrete(&self.param(&modes, 0)?, "relative base adjustment")?;
+                    self.base = self.base.wrapping_add(value);
+                    self.iptr += 2;
+                },
+                Opcode::Halt => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Values of all variables for which `expr` equals `target`, `None` if there are none.
+    pub fn solve(&self, expr: &Expr, target: i64) -> VmResult<Option<Vec<i64>>> {
+        let mut vars: Vec<i64> = self.variables.iter().map(|variable| variable.range.start).collect();
+        if let Some((sum, terms)) = expr.linear() {
+            return self.solve_linear(sum, &terms, target, &mut vars)
+        }
+
+        let free: Vec<usize> = expr.variables().into_iter().collect();
+        self.check_candidates(&free)?;
+        Ok(self.search(&free, &mut vars, &|vars: &mut [i64]| expr.eval(vars) == Some(target)))
+    }
+
+    fn solve_linear(&self, sum: i64, terms: &BTreeMap<usize, i64>, target: i64, vars: &mut [i64]) -> VmResult<Option<Vec<i64>>> {
+        // the variable with the widest range is computed, the others are enumerated
+        let solved = match terms.keys().max_by_key(|&&index| self.span(index)) {
+            Some(&index) => index,
+            None => return Ok(if sum == target { Some(vars.to_vec()) } else { None }),
+        };
+        let free: Vec<usize> = terms.keys().cloned().filter(|&index| index != solved).collect();
+        self.check_candidates(&free)?;
+
+        let coefficient = terms[&solved];
+        let range = self.variables[solved].range.clone();
+        let accept = |vars: &mut [i64]| -> bool {
+            let rest = free.iter().fold(target.wrapping_sub(sum), |rest, &index| rest.wrapping_sub(terms[&index].wrapping_mul(vars[index])));
+            match divide(rest, coefficient, &range) {
+                Some(value) => {
+                    vars[solved] = value;
+                    true
+                },
+                None => false,
+            }
+        };
+        Ok(self.search(&free, vars, &accept))
+    }
+
+    fn span(&self, index: usize) -> u128 {
+        let range = &self.variables[index].range;
+        (range.end as i128 - range.start as i128).max(0) as u128
+    }
+
+    fn check_candidates(&self, free: &[usize]) -> VmResult<()> {
+        let candidates = free.iter().try_fold(1u128, |total, &index| total.checked_mul(self.span(index)));
+        match candidates {
+            Some(candidates) if candidates <= MAX_CANDIDATES => Ok(()),
+            _ => Err(too_complex(format!("too many candidate values for {}", free.iter()
+                .map(|&index| self.variables[index].name.as_str()).collect::<Vec<_>>().join(", ")))),
+        }
+    }
+
+    /// Tries every combination of the `free` variables, `accept` may fill in the others.
+    fn search(&self, free: &[usize], vars: &mut [i64], accept: &dyn Fn(&mut [i64]) -> bool) -> Option<Vec<i64>> {
+        let (&index, rest) = match free.split_first() {
+            Some(split) => split,
+            None => return if accept(vars) { Some(vars.to_vec()) } else { None },
+        };
+        for value in self.variables[index].range.clone() {
+            vars[index] = value;
+            if let Some(solution) = self.search(rest, vars, accept) {
+                return Some(solution)
+            }
+        }
+        None
+    }
+}
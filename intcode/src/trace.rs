@@ -0,0 +1,196 @@
+//! Recording executed instructions and replaying them afterwards.
+//!
+//! A `Trace` keeps one `Event` per executed instruction and is stored as JSON lines.
+//! `Replay` walks the recorded events over the initial memory in both directions,
+//! which is how one finds the step that put a bad value into a cell.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Cell, Intcode, Memory, MemoryLimits, Op, PagedMemory, VmResult};
+
+
+/// Gets called around every instruction executed by `Intcode::run_traced`.
+///
+/// `C` is the cell type of the machine, see `cell`.
+pub trait Tracer<C: Cell = i64> {
+    fn before_step<M: Memory<Cell = C>>(&mut self, _vm: &Intcode<M>) {}
+
+    fn after_step<M: Memory<Cell = C>>(&mut self, _vm: &Intcode<M>) {}
+}
+
+impl<C: Cell> Tracer<C> for () {}
+
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct MemWrite {
+    pub addr: usize,
+    pub old: i64,
+    pub new: i64
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub step: usize,
+    pub iptr: usize,
+    pub base: i64,
+    pub op: Op,
+    pub write: Option<MemWrite>,
+    pub input: Option<i64>,
+    pub output: Option<i64>
+}
+
+
+#[derive(Debug, Default)]
+pub struct Trace {
+    pub events: Vec<Event>,
+    // filled in before the instruction runs, completed after it
+    pending: Option<Event>
+}
+
+impl Tracer for Trace {
+    fn before_step<M: Memory<Cell = i64>>(&mut self, vm: &Intcode<M>) {
+        let write = vm.op.write_addr().map(|addr| MemWrite{addr, old: vm.peek(addr), new: 0});
+        self.pending = Some(Event{
+            step: self.events.len(),
+            iptr: vm.iptr,
+            base: vm.base,
+            op: vm.op.clone(),
+            write,
+            input: None,
+            output: None
+        });
+    }
+
+    fn after_step<M: Memory<Cell = i64>>(&mut self, vm: &Intcode<M>) {
+        if let Some(mut event) = self.pending.take() {
+            if let Some(write) = event.write.as_mut() {
+                write.new = vm.peek(write.addr);
+            }
+            match event.op {
+                Op::Input(_) => event.input = event.write.map(|write| write.new),
+                Op::Output(value) => event.output = Some(value),
+                _ => {},
+            }
+            self.events.push(event);
+        }
+    }
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes one JSON object per line.
+    pub fn save<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for event in &self.events {
+            serde_json::to_writer(&mut *writer, event)?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    pub fn load<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut events: Vec<Event> = vec![];
+        for line in reader.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                events.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(Self{events, pending: None})
+    }
+
+    /// Every value the program has read, in order.
+    pub fn inputs(&self) -> Vec<i64> {
+        self.events.iter().filter_map(|event| event.input).collect()
+    }
+}
+
+
+/// Moves through a recorded run over a copy of the memory.
+///
+/// `position` is the number of applied events, i.e. `events[position]` is executed next.
+pub struct Replay {
+    pub memory: PagedMemory,
+    pub events: Vec<Event>,
+    pub position: usize
+}
+
+impl Replay {
+    pub fn new(code: Vec<i64>, trace: Trace) -> Self {
+        // every recorded write has passed the limits of the run already
+        let limits = MemoryLimits{max_addr: usize::MAX, max_pages: usize::MAX};
+        Self{memory: PagedMemory::with_limits(&code, limits), events: trace.events, position: 0}
+    }
+
+    fn set(&mut self, addr: usize, value: i64) {
+        let _ = self.memory.write(addr, value);
+    }
+
+    pub fn peek(&self, addr: usize) -> i64 {
+        self.memory.read(addr).unwrap_or(0)
+    }
+
+    /// Applies the next event, `None` at the end of the run.
+    pub fn forward(&mut self) -> Option<&Event> {
+        let write = self.events.get(self.position)?.write;
+        if let Some(write) = write {
+            self.set(write.addr, write.new);
+        }
+        self.position += 1;
+        self.events.get(self.position - 1)
+    }
+
+    /// Undoes the last applied event, `None` at the beginning of the run.
+    pub fn backward(&mut self) -> Option<&Event> {
+        if self.position == 0 {
+            return None
+        }
+        self.position -= 1;
+        if let Some(write) = self.events[self.position].write {
+            self.set(write.addr, write.old);
+        }
+        self.events.get(self.position)
+    }
+
+    pub fn seek(&mut self, position: usize) {
+        let position = position.min(self.events.len());
+        while self.position < position {
+            self.forward();
+        }
+        while self.position > position {
+            self.backward();
+        }
+    }
+
+    /// The applied event that wrote the current value of `addr`.
+    pub fn last_write(&self, addr: usize) -> Option<&Event> {
+        self.events[..self.position].iter().rev()
+            .find(|event| event.write.map(|write| write.addr) == Some(addr))
+    }
+
+    /// Every recorded write to `addr`, oldest first.
+    pub fn history(&self, addr: usize) -> Vec<&Event> {
+        self.events.iter().filter(|event| event.write.map(|write| write.addr) == Some(addr)).collect()
+    }
+
+    /// Executes `program` again with the recorded inputs and compares it with the log.
+    ///
+    /// Returns the first step where the runs differ, `None` if the run is reproduced exactly.
+    pub fn verify<M: Memory<Cell = i64>>(&self, mut program: Intcode<M>) -> VmResult<Option<usize>> {
+        let mut input: VecDeque<i64> = self.events.iter().filter_map(|event| event.input).collect();
+        let mut output: Vec<i64> = vec![];
+        let mut trace = Trace::new();
+        program.run_traced(&mut input, &mut output, &mut trace)?;
+
+        let diverged = self.events.iter().zip(&trace.events).position(|(recorded, actual)| recorded != actual);
+        if diverged.is_none() && self.events.len() != trace.events.len() {
+            return Ok(Some(self.events.len().min(trace.events.len())))
+        }
+        Ok(diverged)
+    }
+}
@@ -0,0 +1,40 @@
+use std::io::Cursor;
+
+use intcode::ascii::{decode, terminal, Chunk};
+use intcode::asm::assemble;
+use intcode::{Intcode, PagedMemory, RunState};
+
+
+fn text(value: &str) -> Chunk {
+    Chunk::Text(value.to_string())
+}
+
+#[test]
+fn raw_values_stay_in_place() {
+    let values: Vec<i64> = "map\n".bytes().map(i64::from).chain(vec![1000, 33, -1, 128, 10, 10]).collect();
+    assert_eq!(decode(&values), vec![text("map\n"), Chunk::Raw(1000), text("!"), Chunk::Raw(-1), Chunk::Raw(128), text("\n\n")]);
+}
+
+#[test]
+fn only_raw_values() {
+    assert_eq!(decode(&[1000, 2000]), vec![Chunk::Raw(1000), Chunk::Raw(2000)]);
+    assert_eq!(decode(&[]), vec![]);
+}
+
+#[test]
+fn terminal_prints_chunks_in_order() {
+    let code = assemble("
+                in -> [c]
+                out [c]
+                out #500
+                in -> [c]
+                out [c]
+                hlt
+        c:      .data 0
+    ").unwrap();
+    let mut program = Intcode::with_memory(PagedMemory::new(&code)).unwrap();
+    let mut screen: Vec<u8> = vec![];
+    let state = terminal(&mut program, &mut Cursor::new("a\n"), &mut screen).unwrap();
+    assert_eq!(state, RunState::Halted);
+    assert_eq!(String::from_utf8(screen).unwrap(), "$ a> 500\n\n");
+}
@@ -0,0 +1,49 @@
+use std::fs::read_to_string;
+
+use intcode::asm::{assemble, to_program};
+use intcode::disasm::{disassemble, listing};
+
+
+// puzzle inputs of the days that run on the VM, relative to the crate
+const INPUTS: &[&str] = &[
+    "../day2/src/input.txt",
+    "../day5/src/input.txt",
+    "../day7/src/input.txt",
+    "../day9/src/input.txt",
+    "../day11/src/input.txt",
+    "../day13/src/input.txt",
+];
+
+#[test]
+fn disassembled_inputs_assemble_back() {
+    for path in INPUTS {
+        let code = intcode::parse(&read_to_string(path).unwrap()).unwrap();
+        let text = listing(&disassemble(&code));
+        match assemble(&text) {
+            Ok(assembled) => assert!(assembled == code, "{} does not assemble back to itself", path),
+            Err(error) => panic!("{}: {}", path, error),
+        }
+    }
+}
+
+#[test]
+fn assembled_program_disassembles_back() {
+    let text = "
+        start:  in -> [x]
+                mul [x], #2 -> [x]
+                out [x]
+                jt #1, #start
+        x:      .data 0
+    ";
+    let code = assemble(text).unwrap();
+    assert_eq!(to_program(&code), "3,11,1002,11,2,11,4,11,1105,1,0,0");
+    // labels are gone, but the listing is the same program again
+    assert_eq!(assemble(&listing(&disassemble(&code))).unwrap(), code);
+}
+
+#[test]
+fn relative_operands() {
+    let code = assemble("arb #5\nadd [r+1], [r-2] -> [r+0]\nhlt").unwrap();
+    assert_eq!(code, vec![109, 5, 22201, 1, -2, 0, 99]);
+    assert_eq!(assemble(&listing(&disassemble(&code))).unwrap(), code);
+}
@@ -0,0 +1,27 @@
+use intcode::conformance::{check, corpus, Big, Checked, Engine, Fast, Interpreter};
+
+
+fn assert_conforms<E: Engine>(engine: &E) {
+    let mismatches: Vec<String> = check(engine, &corpus()).iter().map(|mismatch| mismatch.to_string()).collect();
+    assert!(mismatches.is_empty(), "{} mismatches:\n{}", mismatches.len(), mismatches.join("\n"));
+}
+
+#[test]
+fn interpreter() {
+    assert_conforms(&Interpreter);
+}
+
+#[test]
+fn fast() {
+    assert_conforms(&Fast);
+}
+
+#[test]
+fn checked() {
+    assert_conforms(&Checked);
+}
+
+#[test]
+fn big() {
+    assert_conforms(&Big);
+}
@@ -0,0 +1,17 @@
+use intcode::asm::to_program;
+use intcode::fuzz::{check, random_case};
+
+
+// small enough to keep `cargo test` quick, the fuzz binary is there for long runs
+const PROGRAMS: u64 = 300;
+const BUDGET: usize = 1_000;
+
+#[test]
+fn random_programs() {
+    for seed in 0..PROGRAMS {
+        let (code, inputs, split) = random_case(seed, BUDGET);
+        if let Err(problem) = check(&code, &inputs, BUDGET, split) {
+            panic!("seed {}: {}\n  program: {}\n  inputs: {:?}", seed, problem, to_program(&code), inputs);
+        }
+    }
+}
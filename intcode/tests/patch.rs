@@ -0,0 +1,55 @@
+use intcode::patch::{Patch, PatchSet, Program};
+use intcode::{RunState, VmError, IO};
+
+
+fn assert_bad_patch(result: Result<PatchSet, VmError>, reason: &str) {
+    match result {
+        Err(VmError::BadPatch { reason: found }) => assert!(found.contains(reason), "{:?} does not mention {:?}", found, reason),
+        other => panic!("expected a bad patch mentioning {:?}, got {:?}", reason, other),
+    }
+}
+
+#[test]
+fn keys_are_addresses() {
+    let set = PatchSet::from_toml("[free-play]\n0 = 2\n\" 12 \" = -1\n").unwrap();
+    assert_eq!(set.get("free-play").unwrap().cells(), vec![(0, 2), (12, -1)]);
+
+    let set = PatchSet::from_json(r#"{"inputs": {"1": 12, "2": 2}}"#).unwrap();
+    assert_eq!(set.get("inputs").unwrap(), &vec![(1, 12), (2, 2)].into_iter().collect::<Patch>());
+}
+
+#[test]
+fn keys_that_are_not_addresses() {
+    assert_bad_patch(PatchSet::from_toml("[broken]\nnoun = 12\n"), "\"noun\" is not an address");
+    assert_bad_patch(PatchSet::from_toml("[broken]\n-1 = 12\n"), "\"-1\" is not an address");
+    assert_bad_patch(PatchSet::from_json(r#"{"broken": {"0x10": 1}}"#), "\"0x10\" is not an address");
+}
+
+#[test]
+fn duplicate_addresses() {
+    assert_bad_patch(PatchSet::from_toml("[twice]\n1 = 12\n01 = 13\n"), "address 1 is set more than once");
+    assert_bad_patch(PatchSet::from_json(r#"{"twice": {"2": 0, " 2": 1}}"#), "address 2 is set more than once");
+}
+
+#[test]
+fn cells_are_decoded_after_the_whole_patch() {
+    // the unpatched program would read its first parameter from -5
+    let program = Program::new("99,-5,0,0,99").unwrap();
+    let patch: Patch = vec![(0, 1), (1, 4), (2, 4), (3, 0)].into_iter().collect();
+    let mut vm = program.patched(&[&patch]).unwrap();
+    assert_eq!(vm.run(&mut IO::new(false), &mut IO::new(false)).unwrap(), RunState::Halted);
+    assert_eq!(vm.peek(0), 198);
+    // the shared program is left as it was
+    assert_eq!(program.code(), &[99, -5, 0, 0, 99]);
+}
+
+#[test]
+fn instance_decodes_patched_program() {
+    // address 0 holds no valid instruction until the patch fixes it
+    let program = Program::new("0,0,0,0,99").unwrap();
+    assert!(program.instance().is_err());
+    let patch: Patch = vec![(0, 1)].into_iter().collect();
+    let mut vm = program.patched(&[&patch]).unwrap();
+    assert_eq!(vm.run(&mut IO::new(false), &mut IO::new(false)).unwrap(), RunState::Halted);
+    assert_eq!(vm.peek(0), 2);
+}
@@ -0,0 +1,61 @@
+use serde_json::{json, Value};
+
+use intcode::snapshot::Snapshot;
+use intcode::{Intcode, VmError, IO, PAGE_SIZE};
+
+
+fn saved(program: &str) -> Value {
+    let vm = Intcode::new(program).unwrap();
+    let mut json: Vec<u8> = vec![];
+    vm.snapshot(&IO::new(false), &IO::new(false)).save(&mut json).unwrap();
+    serde_json::from_slice(&json).unwrap()
+}
+
+fn load(snapshot: &Value) -> Result<Snapshot, VmError> {
+    Snapshot::load(snapshot.to_string().as_bytes())
+}
+
+fn assert_refused(snapshot: &Value, reason: &str) {
+    match load(snapshot) {
+        Err(VmError::BadSnapshot { reason: found }) => assert!(found.contains(reason), "{:?} does not mention {:?}", found, reason),
+        other => panic!("expected a bad snapshot mentioning {:?}, got {:?}", reason, other),
+    }
+}
+
+#[test]
+fn round_trip() {
+    let snapshot = load(&saved("1,0,0,0,99")).unwrap();
+    let (mut vm, mut input, mut output) = Intcode::from_snapshot(snapshot).unwrap();
+    vm.run(&mut input, &mut output).unwrap();
+    assert_eq!(vm.peek(0), 2);
+}
+
+#[test]
+fn short_page() {
+    let mut snapshot = saved("1,0,0,0,99");
+    snapshot["vm"]["memory"]["pages"] = json!({"0": [1, 10, 10, 0, 99]});
+    assert_refused(&snapshot, "page 0 has 5 cells");
+}
+
+#[test]
+fn missing_program_page() {
+    let mut snapshot = saved("1,0,0,0,99");
+    snapshot["vm"]["memory"]["len"] = json!(PAGE_SIZE + 1);
+    assert_refused(&snapshot, "page 1 of the");
+}
+
+#[test]
+fn page_beyond_limits() {
+    let mut snapshot = saved("1,0,0,0,99");
+    snapshot["vm"]["memory"]["limits"]["max_addr"] = json!(PAGE_SIZE - 1);
+    snapshot["vm"]["memory"]["pages"]["1"] = json!(vec![0; PAGE_SIZE]);
+    assert_refused(&snapshot, "page 1 lies beyond");
+}
+
+#[test]
+fn too_many_pages() {
+    let mut snapshot = saved("1,0,0,0,99");
+    snapshot["vm"]["memory"]["limits"]["max_pages"] = json!(1);
+    snapshot["vm"]["memory"]["pages"]["7"] = json!(vec![0; PAGE_SIZE]);
+    assert_refused(&snapshot, "2 pages are allocated");
+}
@@ -0,0 +1,82 @@
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use intcode::symbolic::Symbolic;
+
+
+// far more than any of the programs below needs
+const MAX_STEPS: u64 = 1_000;
+
+/// Solves `program` for the cell at `addr` becoming `target`, with `x` from `range` at `addr`.
+fn solve(code: &[i64], addr: usize, range: Range<i64>, target: i64) -> Option<i64> {
+    let mut program = Symbolic::new(code);
+    let x = program.variable("x", range);
+    program.poke(addr, x);
+    program.run(MAX_STEPS).unwrap();
+    program.solve(&program.peek(addr), target).unwrap().map(|values| values[0])
+}
+
+/// `x` doubled `times` times in place, followed by `rest` and the cell holding `x`.
+fn doubling(times: usize, rest: &[i64]) -> (Vec<i64>, usize) {
+    let addr = 4 * times + rest.len() + 1;
+    let mut code: Vec<i64> = vec![];
+    for _ in 0..times {
+        code.extend(&[1, addr as i64, addr as i64, addr as i64]);
+    }
+    code.extend(rest);
+    code.extend(&[99, 0]);
+    (code, addr)
+}
+
+#[test]
+fn doubling_loop_is_linear() {
+    let start = Instant::now();
+    let (code, addr) = doubling(40, &[]);
+    assert_eq!(solve(&code, addr, 0..100, 7 << 40), Some(7));
+    assert!(start.elapsed() < Duration::from_secs(1), "took {:?}", start.elapsed());
+}
+
+#[test]
+fn doubling_loop_is_enumerated() {
+    let start = Instant::now();
+    // x * 2^40 < 5 * 2^40, stored back into the cell of x, with 5 * 2^40 right after it
+    let (mut code, addr) = doubling(40, &[7, 165, 166, 165]);
+    assert_eq!(addr, 165);
+    code.push(5 << 40);
+    assert_eq!(solve(&code, addr, 0..100, 0), Some(5));
+    assert!(start.elapsed() < Duration::from_secs(1), "took {:?}", start.elapsed());
+}
+
+/// `coefficient * x`, with `x` in the cell after the halt.
+fn scaled(coefficient: i64) -> (Vec<i64>, usize) {
+    (vec![1002, 5, coefficient, 5, 99, 0], 5)
+}
+
+#[test]
+fn divide_odd_coefficient() {
+    let (code, addr) = scaled(3);
+    assert_eq!(solve(&code, addr, 0..1 << 40, 3 * 123_456_789), Some(123_456_789));
+    // 3 is invertible, so every target has exactly one solution modulo 2^64
+    let x = 0x5555_5555_5555_5556;
+    assert_eq!(solve(&code, addr, 0..i64::MAX, 2), Some(x));
+}
+
+#[test]
+fn divide_even_coefficient() {
+    let (code, addr) = scaled(6);
+    assert_eq!(solve(&code, addr, 0..1 << 40, 6 * 1000), Some(1000));
+    assert_eq!(solve(&code, addr, 0..1 << 40, 7), None);
+}
+
+#[test]
+fn divide_wraps_around() {
+    // solutions repeat every 2^62, the smallest one in the range is returned
+    let (code, addr) = scaled(-4);
+    let x = (1 << 61) + 5;
+    assert_eq!(solve(&code, addr, 0..i64::MAX, (-4i64).wrapping_mul(x)), Some(x));
+    assert_eq!(solve(&code, addr, (1 << 61) + 6..i64::MAX, (-4i64).wrapping_mul(x)), Some(x + (1 << 62)));
+
+    let (code, addr) = scaled(4);
+    assert_eq!(solve(&code, addr, -(1 << 62)..0, 40), Some(10 - (1 << 62)));
+    assert_eq!(solve(&code, addr, 11..(1 << 62) + 10, 40), None);
+}